@@ -0,0 +1,85 @@
+// This file defines the command-line interface of the application.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::columns::Column;
+use crate::fs::sort::SortKey;
+use crate::output::Format;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Interactive terminal file picker")]
+pub struct Args {
+    /// Directory to start browsing in
+    #[arg(value_name = "DIR", default_value = ".")]
+    pub dir: PathBuf,
+
+    /// Show hidden files (names starting with a dot)
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Also list paths ignored by .gitignore, .ignore and .git/info/exclude
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Only list files with these extensions, e.g. rs,toml
+    #[arg(long, value_name = "EXTS", value_delimiter = ',')]
+    pub ext: Vec<String>,
+
+    /// Only list files whose name matches this glob (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub glob: Vec<String>,
+
+    /// Order entries by this key (cycle with `s` while running)
+    #[arg(long, value_enum, value_name = "KEY", default_value_t = SortKey::Name)]
+    pub sort: SortKey,
+
+    /// Reverse the sort order (toggle with `S` while running)
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Mix directories in with files instead of listing them first
+    #[arg(long)]
+    pub mixed: bool,
+
+    /// Metadata columns to show next to names
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub columns: Vec<Column>,
+
+    /// Show modification times as dates instead of "3d ago"
+    #[arg(long)]
+    pub absolute_time: bool,
+
+    /// Expand directories this many levels deep on startup
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub depth: usize,
+
+    /// Expand the tree down to this path and select it
+    #[arg(long, value_name = "PATH")]
+    pub reveal: Option<PathBuf>,
+
+    /// Print the chosen path to stdout and exit instead of opening it
+    #[arg(long)]
+    pub pick: bool,
+
+    /// Pick directories instead of files (implies --pick)
+    #[arg(long, conflicts_with = "save")]
+    pub directory: bool,
+
+    /// Choose a location and type a file name to save to (implies --pick)
+    #[arg(long, value_name = "DEFAULT_NAME", num_args = 0..=1, default_missing_value = "")]
+    pub save: Option<String>,
+
+    /// How picked paths are written to stdout
+    #[arg(long, value_enum, default_value_t = Format::Lines)]
+    pub format: Format,
+
+    /// Separate picked paths with NUL instead of newline (for xargs -0)
+    #[arg(long, conflicts_with = "format")]
+    pub print0: bool,
+
+    /// Print picked paths relative to this directory instead of absolute
+    #[arg(long, value_name = "DIR")]
+    pub relative_to: Option<PathBuf>,
+}
@@ -0,0 +1,214 @@
+// This file defines the optional metadata columns shown next to each name in the
+// tree (size, modified time, permissions, owner) and how their cells are formatted.
+
+use std::time::SystemTime;
+
+use clap::ValueEnum;
+use unicode_width::UnicodeWidthStr;
+
+use crate::fs::owners;
+use crate::fs::tree::{DirSize, FlatItem};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// Human-readable size
+    Size,
+    /// Modification time
+    Mtime,
+    /// Permission string like rwxr-xr-x
+    Perms,
+    /// Owner and group
+    Owner,
+}
+
+impl Column {
+    pub fn label(self) -> &'static str {
+        match self {
+            Column::Size => "size",
+            Column::Mtime => "mtime",
+            Column::Perms => "permissions",
+            Column::Owner => "owner",
+        }
+    }
+}
+
+// Display order, ls style.
+const ORDER: [Column; 4] = [Column::Perms, Column::Owner, Column::Size, Column::Mtime];
+// Which columns give way first when the tree is too narrow for all of them.
+const DROP_ORDER: [Column; 4] = [Column::Owner, Column::Perms, Column::Mtime, Column::Size];
+
+#[derive(Debug, Default, Clone)]
+pub struct Columns {
+    enabled: Vec<Column>,
+    pub absolute_time: bool, // dates instead of "3d ago"
+}
+
+impl Columns {
+    pub fn new(enabled: &[Column]) -> Self {
+        let mut columns = Self::default();
+        for &c in enabled {
+            if !columns.is_enabled(c) {
+                columns.enabled.push(c);
+            }
+        }
+        columns
+    }
+
+    pub fn is_enabled(&self, column: Column) -> bool {
+        self.enabled.contains(&column)
+    }
+
+    // Returns whether the column is now shown.
+    pub fn toggle(&mut self, column: Column) -> bool {
+        if self.is_enabled(column) {
+            self.enabled.retain(|&c| c != column);
+            false
+        } else {
+            self.enabled.push(column);
+            true
+        }
+    }
+
+    // The enabled columns that fit next to names in `width` cells, in display order,
+    // each with its cell width.
+    pub fn fit(&self, rows: &[FlatItem], width: u16, min_name: u16) -> Vec<(Column, usize)> {
+        let mut shown: Vec<(Column, usize)> = ORDER
+            .iter()
+            .filter(|c| self.is_enabled(**c))
+            .map(|&c| (c, self.width(c, rows)))
+            .collect();
+        for drop in DROP_ORDER {
+            let used: usize = shown.iter().map(|(_, w)| w + 1).sum();
+            if width as usize >= min_name as usize + used {
+                break;
+            }
+            shown.retain(|(c, _)| *c != drop);
+        }
+        shown
+    }
+
+    fn width(&self, column: Column, rows: &[FlatItem]) -> usize {
+        match column {
+            Column::Size => 6,
+            Column::Mtime if self.absolute_time => 16,
+            Column::Mtime => 8,
+            Column::Perms => 10,
+            // sized to the longest owner among the rows, within reason
+            Column::Owner => rows
+                .iter()
+                .map(|r| owner(r).width())
+                .max()
+                .unwrap_or(0)
+                .min(20),
+        }
+    }
+
+    pub fn cell(&self, column: Column, row: &FlatItem, now: SystemTime) -> String {
+        match column {
+            Column::Size if row.is_dir => match row.usage {
+                Some(u) => usage(u),
+                None => "-".to_string(),
+            },
+            Column::Size => human_size(row.size),
+            Column::Mtime => match row.modified {
+                Some(t) if self.absolute_time => absolute_time(t),
+                Some(t) => relative_time(t, now),
+                None => "-".to_string(),
+            },
+            Column::Perms => row.mode.map(permissions).unwrap_or_else(|| "-".to_string()),
+            Column::Owner => owner(row),
+        }
+    }
+}
+
+// "512B", "1.5K", "12K", "3.0G": one decimal below ten, whole numbers above.
+pub fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64;
+    let mut unit = 'B';
+    for u in ['K', 'M', 'G', 'T', 'P', 'E'] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = u;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, unit)
+    } else {
+        format!("{:.0}{}", value, unit)
+    }
+}
+
+// A directory's total, marked while it is still being counted.
+pub fn usage(size: DirSize) -> String {
+    if size.done {
+        human_size(size.bytes)
+    } else {
+        format!("{}…", human_size(size.bytes))
+    }
+}
+
+fn relative_time(t: SystemTime, now: SystemTime) -> String {
+    let Ok(age) = now.duration_since(t) else {
+        return absolute_time(t); // in the future, e.g. clock skew
+    };
+    let secs = age.as_secs();
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86_400 => format!("{}h ago", secs / 3600),
+        86_400..2_592_000 => format!("{}d ago", secs / 86_400),
+        2_592_000..31_536_000 => format!("{}mo ago", secs / 2_592_000),
+        _ => format!("{}y ago", secs / 31_536_000),
+    }
+}
+
+pub fn absolute_time(t: SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(t)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+// ls-style mode string, e.g. "drwxr-xr-x" or "-rwsr-xr-x".
+pub fn permissions(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut out = String::with_capacity(10);
+    out.push(kind);
+    // (read, write, execute bits, special bit, char for special+exec, special alone)
+    let triads = [
+        (0o400, 0o200, 0o100, 0o4000, 's', 'S'),
+        (0o040, 0o020, 0o010, 0o2000, 's', 'S'),
+        (0o004, 0o002, 0o001, 0o1000, 't', 'T'),
+    ];
+    for (r, w, x, special, on, off) in triads {
+        out.push(if mode & r != 0 { 'r' } else { '-' });
+        out.push(if mode & w != 0 { 'w' } else { '-' });
+        out.push(match (mode & x != 0, mode & special != 0) {
+            (true, true) => on,
+            (false, true) => off,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+fn owner(row: &FlatItem) -> String {
+    match (row.uid, row.gid) {
+        (Some(uid), Some(gid)) => {
+            format!("{}:{}", owners::user_name(uid), owners::group_name(gid))
+        }
+        _ => "-".to_string(),
+    }
+}
@@ -0,0 +1,335 @@
+// This file computes the total size of directories on a worker thread. Every file
+// below a directory counts, including hidden and ignored ones, and running totals
+// are streamed back over the task channel so the tree can show them while the walk
+// is still going.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fs::tree::{DirSize, FileNode};
+use crate::tasks::TaskResult;
+
+const PROGRESS_EVERY: Duration = Duration::from_millis(100);
+
+// Running or final total for a directory, tagged with the walk it came from so
+// reports from canceled or outdated walks can be told apart.
+pub struct Update {
+    walk: u64,
+    dir: PathBuf,
+    bytes: u64,
+    done: bool,
+}
+
+pub struct DiskUsage {
+    pub sizes: HashMap<PathBuf, DirSize>, // running or final total per directory
+    pending: HashMap<PathBuf, u64>,       // directories being walked, and which walk counts
+    next_walk: u64,
+    cancel: Arc<AtomicBool>,
+    dirty: bool, // totals changed since they were last copied onto the tree
+}
+
+impl DiskUsage {
+    pub fn new() -> Self {
+        Self {
+            sizes: HashMap::new(),
+            pending: HashMap::new(),
+            next_walk: 0,
+            cancel: Arc::new(AtomicBool::new(false)),
+            dirty: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Whether `dir` has a final total that is still current.
+    pub fn sized(&self, dir: &Path) -> bool {
+        self.sizes.get(dir).is_some_and(|s| s.done)
+    }
+
+    // Size `dirs` from scratch on a new worker, skipping ones already in progress.
+    // Returns how many were queued.
+    pub fn start(&mut self, dirs: Vec<PathBuf>, tx: Sender<TaskResult>) -> usize {
+        let mut walks = Vec::new();
+        for dir in dirs {
+            if !self.pending.contains_key(&dir) {
+                self.pending.insert(dir.clone(), self.next_walk);
+                walks.push((dir, self.next_walk));
+                self.next_walk += 1;
+            }
+        }
+        if walks.is_empty() {
+            return 0;
+        }
+        let count = walks.len();
+        let cancel = self.cancel.clone();
+        thread::spawn(move || {
+            for (dir, id) in walks {
+                let Some(bytes) = walk(&dir, id, &tx, &cancel) else {
+                    return;
+                };
+                let done = Update {
+                    walk: id,
+                    dir,
+                    bytes,
+                    done: true,
+                };
+                if tx.send(TaskResult::DirSize(done)).is_err() {
+                    return;
+                }
+            }
+        });
+        count
+    }
+
+    // Take in a total reported by a worker. Returns false for stale reports.
+    pub fn receive(&mut self, update: Update) -> bool {
+        if self.pending.get(&update.dir) != Some(&update.walk) {
+            return false;
+        }
+        if update.done {
+            self.pending.remove(&update.dir);
+        }
+        let size = DirSize {
+            bytes: update.bytes,
+            done: update.done,
+        };
+        self.sizes.insert(update.dir, size);
+        self.dirty = true;
+        true
+    }
+
+    // Forget the totals that a change to the entries of `dir` made stale: its own,
+    // every ancestor's, and those below children of it that are gone. Walks still
+    // counting them are dropped too, so what they report later is ignored.
+    pub fn invalidate(&mut self, dir: &Path) {
+        let current = |path: &PathBuf| {
+            if dir.starts_with(path) {
+                return false;
+            }
+            match path
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|rest| rest.iter().next())
+            {
+                Some(child) => fs::symlink_metadata(dir.join(child)).is_ok(),
+                None => true,
+            }
+        };
+        let before = self.sizes.len();
+        self.sizes.retain(|path, _| current(path));
+        self.pending.retain(|path, _| current(path));
+        self.dirty |= self.sizes.len() != before;
+    }
+
+    // The tree got new nodes; copy the totals onto them at the next apply.
+    pub fn reapply(&mut self) {
+        self.dirty = true;
+    }
+
+    // Stop all running walks. Partial totals are dropped.
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.cancel = Arc::new(AtomicBool::new(false));
+        for (dir, _) in self.pending.drain() {
+            self.sizes.remove(&dir);
+        }
+        self.dirty = true;
+    }
+
+    // Copy the known totals onto the tree if they changed since the last call.
+    // Returns whether any node changed.
+    pub fn apply(&mut self, nodes: &mut [FileNode]) -> bool {
+        if !std::mem::take(&mut self.dirty) {
+            return false;
+        }
+        copy_totals(&self.sizes, nodes)
+    }
+}
+
+impl Drop for DiskUsage {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// Set each directory's usage to its known total, if any.
+fn copy_totals(sizes: &HashMap<PathBuf, DirSize>, nodes: &mut [FileNode]) -> bool {
+    let mut changed = false;
+    for node in nodes.iter_mut().filter(|n| n.is_dir) {
+        let size = sizes.get(&node.path).copied();
+        if node.usage != size {
+            node.usage = size;
+            changed = true;
+        }
+        changed |= copy_totals(sizes, &mut node.children);
+    }
+    changed
+}
+
+// Sum the sizes of all files below `root`, sending running totals. Symlinks are
+// counted as links, not followed. None when canceled or the UI went away.
+fn walk(root: &Path, id: u64, tx: &Sender<TaskResult>, cancel: &AtomicBool) -> Option<u64> {
+    let mut total = 0;
+    let mut last_sent = Instant::now();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else {
+                total += meta.len();
+            }
+            if last_sent.elapsed() >= PROGRESS_EVERY {
+                let progress = Update {
+                    walk: id,
+                    dir: root.to_path_buf(),
+                    bytes: total,
+                    done: false,
+                };
+                tx.send(TaskResult::DirSize(progress)).ok()?;
+                last_sent = Instant::now();
+            }
+        }
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver};
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "file-picker-du-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, rel: &str, len: usize) -> PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, vec![0; len]).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Hand everything the walks report to `du` until they are all finished, or
+    // until the workers are gone.
+    fn settle(du: &mut DiskUsage, rx: &Receiver<TaskResult>) {
+        while du.running() {
+            match rx.recv_timeout(Duration::from_secs(5)) {
+                Ok(TaskResult::DirSize(update)) => {
+                    du.receive(update);
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+    }
+
+    #[test]
+    fn counts_every_file_below() {
+        let scratch = Scratch::new("count");
+        scratch.file("a/one", 10);
+        scratch.file("a/deep/two", 20);
+        let (tx, rx) = mpsc::channel();
+        let mut du = DiskUsage::new();
+        let dir = scratch.0.join("a");
+        assert_eq!(du.start(vec![dir.clone(), dir.clone()], tx.clone()), 1);
+        assert_eq!(du.start(vec![dir.clone()], tx), 0);
+        settle(&mut du, &rx);
+        assert!(du.sized(&dir));
+        assert_eq!(du.sizes[&dir].bytes, 30);
+    }
+
+    #[test]
+    fn invalidate_drops_walks_in_progress() {
+        let scratch = Scratch::new("invalidate");
+        scratch.file("a/one", 10);
+        scratch.file("b/one", 5);
+        let (a, b) = (scratch.0.join("a"), scratch.0.join("b"));
+        let (tx, rx) = mpsc::channel();
+        let mut du = DiskUsage::new();
+        du.start(vec![a.clone(), b.clone()], tx.clone());
+        // whatever the walks found so far sits in the channel until received
+        scratch.file("a/two", 20);
+        du.invalidate(&a);
+        assert_eq!(du.pending(), 1);
+
+        let mut stale = 0;
+        while let Ok(TaskResult::DirSize(update)) = rx.recv_timeout(Duration::from_secs(5)) {
+            let from_a = update.dir == a;
+            if !du.receive(update) {
+                assert!(from_a);
+                stale += 1;
+            }
+            if !du.running() && stale > 0 {
+                break;
+            }
+        }
+        assert!(!du.sizes.contains_key(&a));
+        assert_eq!(du.sizes[&b].bytes, 5);
+
+        // counting it again starts afresh and sees the change
+        du.start(vec![a.clone()], tx);
+        settle(&mut du, &rx);
+        assert_eq!(du.sizes[&a].bytes, 30);
+    }
+
+    #[test]
+    fn invalidate_drops_ancestors_and_gone_children() {
+        let scratch = Scratch::new("ancestors");
+        scratch.file("top/mid/keep/f", 1);
+        scratch.file("top/mid/gone/f", 1);
+        let (tx, rx) = mpsc::channel();
+        let mut du = DiskUsage::new();
+        let [top, mid, keep, gone] =
+            ["top", "top/mid", "top/mid/keep", "top/mid/gone"].map(|p| scratch.0.join(p));
+        du.start(
+            vec![top.clone(), mid.clone(), keep.clone(), gone.clone()],
+            tx,
+        );
+        settle(&mut du, &rx);
+        fs::remove_dir_all(&gone).unwrap();
+        du.invalidate(&mid);
+        let mut left: Vec<&PathBuf> = du.sizes.keys().collect();
+        left.sort();
+        assert_eq!(left, [&keep]);
+    }
+}
@@ -98,11 +98,11 @@ pub fn open_path(path: &std::path::Path) -> Result<(), String> {
     // 3) Other OS: xdg-open fallback
     #[cfg(not(target_os = "macos"))]
     {
-        return Command::new("xdg-open")
+        Command::new("xdg-open")
             .arg(path)
             .spawn()
             .map(|_| ())
-            .map_err(|e| e.to_string());
+            .map_err(|e| e.to_string())
     }
 }
 
@@ -0,0 +1,246 @@
+// This file implements the project-wide file finder. A worker thread walks the whole
+// root (not just what is expanded) and streams paths back, which are fuzzy-ranked
+// against the query as they arrive. Only the hits that can be shown are kept in
+// order, and typing more of the query only re-scores what matched before.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::fs::filter::ListOptions;
+use crate::fuzzy;
+
+const BATCH: usize = 512;
+const TOP: usize = 200; // hits kept in order past the selection, more than fit on screen
+
+pub struct FinderHit {
+    pub index: usize, // into `Finder::paths`
+    pub score: i64,
+    pub matches: Vec<usize>, // char indices of the path's display string
+}
+
+pub struct Finder {
+    pub query: String,
+    pub paths: Vec<PathBuf>,  // relative to the root
+    pub hits: Vec<FinderHit>, // best first up to `ranked`, in no order after that
+    pub selected: usize,
+    pub done: bool, // the walk has finished
+    root: PathBuf,
+    ranked: usize,
+    rx: Receiver<Vec<PathBuf>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Finder {
+    pub fn start(root: &Path, opts: &ListOptions) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (walk_root, walk_opts, walk_cancel) =
+            (root.to_path_buf(), opts.clone(), cancel.clone());
+        thread::spawn(move || {
+            let mut batch = Vec::with_capacity(BATCH);
+            let mut stack = vec![walk_root.clone()];
+            while let Some(dir) = stack.pop() {
+                if walk_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    continue;
+                };
+                let ignores = walk_opts.ignores_for(&dir);
+                for entry in entries.filter_map(Result::ok) {
+                    let path = entry.path();
+                    // don't follow symlinked directories, they can loop
+                    let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                    let ignored = ignores
+                        .as_ref()
+                        .is_some_and(|ig| ig.is_ignored(&path, is_dir));
+                    if ignored || !walk_opts.includes(&path, is_dir) {
+                        continue;
+                    }
+                    if is_dir {
+                        stack.push(path);
+                    } else if let Ok(rel) = path.strip_prefix(&walk_root) {
+                        batch.push(rel.to_path_buf());
+                        if batch.len() == BATCH && tx.send(std::mem::take(&mut batch)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            let _ = tx.send(batch);
+        });
+
+        Self {
+            query: String::new(),
+            paths: Vec::new(),
+            hits: Vec::new(),
+            selected: 0,
+            done: false,
+            root: root.to_path_buf(),
+            ranked: 0,
+            rx,
+            cancel,
+        }
+    }
+
+    // Pull in whatever the walker has found since the last call and rank it.
+    pub fn poll(&mut self) {
+        let first_new = self.paths.len();
+        loop {
+            match self.rx.try_recv() {
+                Ok(batch) => self.paths.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        if self.paths.len() > first_new {
+            self.score(first_new..self.paths.len());
+        }
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        // a longer query only matches paths the shorter one matched
+        let narrowed = query.starts_with(&self.query);
+        self.query = query;
+        self.selected = 0;
+        let previous = std::mem::take(&mut self.hits);
+        if narrowed {
+            self.score(previous.into_iter().map(|h| h.index));
+        } else {
+            self.score(0..self.paths.len());
+        }
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.hits.is_empty() {
+            return;
+        }
+        let last = self.hits.len() - 1;
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+        self.rank();
+    }
+
+    // Absolute path of the selected hit.
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let hit = self.hits.get(self.selected)?;
+        Some(self.root.join(&self.paths[hit.index]))
+    }
+
+    // Add the paths at `indices` that match the query to the hits.
+    fn score(&mut self, indices: impl Iterator<Item = usize>) {
+        for index in indices {
+            let path = self.paths[index].to_string_lossy();
+            if let Some((score, matches)) = fuzzy::score(&self.query, &path) {
+                self.hits.push(FinderHit {
+                    index,
+                    score,
+                    matches,
+                });
+            }
+        }
+        self.ranked = 0;
+        self.rank();
+    }
+
+    // Put the hits up to TOP past the selection in order, best first, leaving the
+    // rest unsorted behind them.
+    fn rank(&mut self) {
+        let want = (self.selected + TOP).min(self.hits.len());
+        if want <= self.ranked {
+            return;
+        }
+        // walk order breaks ties (e.g. an empty query)
+        let key = |h: &FinderHit| (std::cmp::Reverse(h.score), h.index);
+        let rest = &mut self.hits[self.ranked..];
+        let n = want - self.ranked;
+        if n < rest.len() {
+            rest.select_nth_unstable_by_key(n - 1, key);
+        }
+        rest[..n].sort_unstable_by_key(key);
+        self.ranked = want;
+    }
+}
+
+impl Drop for Finder {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A finder whose walk found `paths`, scored against the empty query as poll
+    // would.
+    fn finder(paths: Vec<PathBuf>) -> Finder {
+        let count = paths.len();
+        let mut finder = Finder {
+            query: String::new(),
+            paths,
+            hits: Vec::new(),
+            selected: 0,
+            done: true,
+            root: PathBuf::from("/"),
+            ranked: 0,
+            rx: mpsc::channel().1,
+            cancel: Arc::default(),
+        };
+        finder.score(0..count);
+        finder
+    }
+
+    // The hits for `query` scored from scratch, fully sorted.
+    fn sorted(finder: &Finder, query: &str) -> Vec<(usize, i64)> {
+        let mut hits: Vec<(usize, i64)> = finder
+            .paths
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| Some((i, fuzzy::score(query, &p.to_string_lossy())?.0)))
+            .collect();
+        hits.sort_by_key(|&(i, score)| (std::cmp::Reverse(score), i));
+        hits
+    }
+
+    fn shown(finder: &Finder) -> Vec<(usize, i64)> {
+        finder.hits[..finder.ranked]
+            .iter()
+            .map(|h| (h.index, h.score))
+            .collect()
+    }
+
+    #[test]
+    fn narrowing_matches_a_fresh_ranking() {
+        let paths = (0..2000)
+            .map(|i| PathBuf::from(format!("src/mod{}/file_{}.rs", i % 37, i)))
+            .collect();
+        let mut finder = finder(paths);
+        for query in ["f", "fi", "fil1", "fil12", "fl", "src/mod3/f"] {
+            finder.set_query(query.to_string());
+            let expected = sorted(&finder, query);
+            assert_eq!(finder.hits.len(), expected.len(), "{}", query);
+            assert_eq!(finder.ranked, expected.len().min(TOP), "{}", query);
+            assert_eq!(shown(&finder), expected[..finder.ranked], "{}", query);
+        }
+    }
+
+    #[test]
+    fn moving_down_ranks_further() {
+        let paths = (0..1000)
+            .map(|i| PathBuf::from(format!("a{}", i)))
+            .collect();
+        let mut finder = finder(paths);
+        finder.set_query("a".to_string());
+        assert_eq!(finder.ranked, TOP);
+        finder.move_selection(TOP as isize);
+        assert_eq!(finder.ranked, 2 * TOP);
+        assert_eq!(shown(&finder), sorted(&finder, "a")[..2 * TOP]);
+    }
+}
@@ -0,0 +1,84 @@
+// This file decides which directory entries are listed when a folder is read.
+
+use std::path::Path;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use super::gitignore::{IgnoreCache, IgnoreChain};
+use super::sort::SortMode;
+
+#[derive(Debug, Default, Clone)]
+pub struct ListOptions {
+    pub show_hidden: bool,       // list dotfiles
+    pub respect_ignore: bool,    // hide paths matched by .gitignore and friends
+    pub extensions: Vec<String>, // lowercase, without the leading dot; empty = any
+    pub globs: Option<GlobSet>,  // matched against file names; None = any
+    pub sort: SortMode,          // applied to every directory as it is read
+    pub ignores: IgnoreCache,    // parsed ignore files, shared by every read
+}
+
+impl ListOptions {
+    pub fn new(show_hidden: bool, extensions: &[String], globs: &[String]) -> Result<Self, String> {
+        let extensions = extensions
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+
+        let globs = if globs.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in globs {
+                let glob =
+                    Glob::new(pattern).map_err(|e| format!("invalid glob {}: {}", pattern, e))?;
+                builder.add(glob);
+            }
+            Some(builder.build().map_err(|e| e.to_string())?)
+        };
+
+        Ok(Self {
+            show_hidden,
+            respect_ignore: true,
+            extensions,
+            globs,
+            sort: SortMode::default(),
+            ignores: IgnoreCache::default(),
+        })
+    }
+
+    // The ignore rules for the entries of `dir`, when they are being respected.
+    pub fn ignores_for(&self, dir: &Path) -> Option<IgnoreChain> {
+        self.respect_ignore
+            .then(|| IgnoreChain::for_dir(dir, &self.ignores))
+    }
+
+    // Directories are only subject to the hidden check so that matching files
+    // further down stay reachable; files must also pass the extension and glob filters.
+    pub fn includes(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(name) = path.file_name() else {
+            return true;
+        };
+        if !self.show_hidden && name.to_string_lossy().starts_with('.') {
+            return false;
+        }
+        if is_dir {
+            return true;
+        }
+
+        if !self.extensions.is_empty() {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase());
+            if !ext.is_some_and(|ext| self.extensions.contains(&ext)) {
+                return false;
+            }
+        }
+        if let Some(globs) = &self.globs
+            && !globs.is_match(name)
+        {
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,196 @@
+// This file evaluates .gitignore, .ignore and .git/info/exclude rules for the
+// entries of a directory.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+// The ignore rules that apply inside one directory: its own ignore files plus those
+// of every ancestor up to the enclosing repository root, deepest first. Outside a
+// git repository none apply.
+pub struct IgnoreChain {
+    levels: Vec<Arc<Level>>, // the last one is the repository root
+}
+
+// What one directory contributes to the chains of the directories below it.
+#[derive(Debug)]
+struct Level {
+    rules: Option<Gitignore>,   // its .gitignore and .ignore
+    exclude: Option<Gitignore>, // .git/info/exclude, at a repository root
+    is_root: bool,              // holds a .git
+}
+
+impl Level {
+    fn read(dir: &Path) -> Self {
+        let mut builder = GitignoreBuilder::new(dir);
+        // later files win, so .ignore overrides .gitignore in the same directory
+        for name in [".gitignore", ".ignore"] {
+            let file = dir.join(name);
+            if file.is_file() {
+                builder.add(file);
+            }
+        }
+        let rules = builder.build().ok().filter(|gi| !gi.is_empty());
+
+        let git_dir = dir.join(".git");
+        let is_root = git_dir.exists();
+        let exclude = is_root
+            .then(|| {
+                let mut builder = GitignoreBuilder::new(dir);
+                builder.add(git_dir.join("info").join("exclude"));
+                builder.build().ok()
+            })
+            .flatten()
+            .filter(|gi| !gi.is_empty());
+        Self {
+            rules,
+            exclude,
+            is_root,
+        }
+    }
+}
+
+// Parsed ignore files by directory, shared by every listing so that reading a
+// folder doesn't parse its ancestors' files all over again.
+#[derive(Debug, Default, Clone)]
+pub struct IgnoreCache(Arc<Mutex<HashMap<PathBuf, Arc<Level>>>>);
+
+impl IgnoreCache {
+    fn level(&self, dir: &Path) -> Arc<Level> {
+        if let Some(level) = self.lock().get(dir) {
+            return level.clone();
+        }
+        // read without holding the lock; a racing reader just parses it twice
+        let level = Arc::new(Level::read(dir));
+        self.lock().insert(dir.to_path_buf(), level.clone());
+        level
+    }
+
+    // Parse the ignore files of `dir` again next time, after it changed.
+    pub fn forget(&self, dir: &Path) {
+        self.lock().remove(dir);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Arc<Level>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl IgnoreChain {
+    pub fn for_dir(dir: &Path, cache: &IgnoreCache) -> Self {
+        let mut levels = Vec::new();
+        for ancestor in dir.ancestors() {
+            let level = cache.level(ancestor);
+            let is_root = level.is_root;
+            levels.push(level);
+            if is_root {
+                return Self { levels };
+            }
+        }
+        // ignore files mean nothing outside a repository
+        Self { levels: Vec::new() }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let rules = self.levels.iter().filter_map(|l| l.rules.as_ref());
+        // the repository's exclude file has the lowest precedence
+        let exclude = self.levels.last().and_then(|l| l.exclude.as_ref());
+        for gi in rules.chain(exclude) {
+            match gi.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "file-picker-gitignore-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, rel: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+
+        fn ignored(&self, cache: &IgnoreCache, rel: &str) -> bool {
+            let path = self.0.join(rel);
+            IgnoreChain::for_dir(path.parent().unwrap(), cache).is_ignored(&path, false)
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn nothing_applies_outside_a_repository() {
+        let scratch = Scratch::new("outside");
+        scratch.file(".gitignore", "*.log\n");
+        scratch.file("sub/.ignore", "*.tmp\n");
+        let cache = IgnoreCache::default();
+        assert!(!scratch.ignored(&cache, "a.log"));
+        assert!(!scratch.ignored(&cache, "sub/a.log"));
+        assert!(!scratch.ignored(&cache, "sub/a.tmp"));
+    }
+
+    #[test]
+    fn rules_stop_at_the_repository_root() {
+        let scratch = Scratch::new("repo");
+        scratch.file(".gitignore", "*.txt\n");
+        scratch.file("repo/.git/info/exclude", "*.bak\n*.keep\n");
+        scratch.file("repo/.gitignore", "*.log\n!keep.log\n");
+        scratch.file("repo/sub/.ignore", "keep.log\n!*.keep\n");
+        let cache = IgnoreCache::default();
+        // the .gitignore above the repository is not part of it
+        assert!(!scratch.ignored(&cache, "repo/a.txt"));
+        assert!(scratch.ignored(&cache, "repo/a.log"));
+        assert!(scratch.ignored(&cache, "repo/a.bak"));
+        assert!(!scratch.ignored(&cache, "repo/keep.log"));
+        // deeper rules take precedence, and the exclude file comes last
+        assert!(scratch.ignored(&cache, "repo/sub/keep.log"));
+        assert!(!scratch.ignored(&cache, "repo/sub/a.keep"));
+        assert!(scratch.ignored(&cache, "repo/sub/deeper/a.log"));
+    }
+
+    #[test]
+    fn parsed_files_are_kept_until_forgotten() {
+        let scratch = Scratch::new("cache");
+        fs::create_dir_all(scratch.0.join(".git")).unwrap();
+        scratch.file(".gitignore", "*.log\n");
+        let cache = IgnoreCache::default();
+        assert!(scratch.ignored(&cache, "sub/a.log"));
+        assert!(cache.lock().contains_key(&scratch.0.join("sub")));
+
+        scratch.file(".gitignore", "*.tmp\n");
+        assert!(scratch.ignored(&cache, "sub/a.log"));
+        cache.forget(&scratch.0);
+        assert!(!scratch.ignored(&cache, "sub/a.log"));
+        assert!(scratch.ignored(&cache, "sub/a.tmp"));
+    }
+}
@@ -1,3 +1,8 @@
 // This file is intentionally left blank.
+pub mod filter;
+pub mod gitignore;
 pub mod icons;
+pub mod owners;
+pub mod sort;
 pub mod tree;
+pub mod view;
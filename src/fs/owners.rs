@@ -0,0 +1,32 @@
+// This file maps numeric user and group ids to names using /etc/passwd and
+// /etc/group. Both files are read once; ids without an entry show as numbers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+fn load(path: &str) -> HashMap<u32, String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    // name:password:id:...
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut fields = l.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+pub fn user_name(uid: u32) -> String {
+    static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    let users = USERS.get_or_init(|| load("/etc/passwd"));
+    users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+}
+
+pub fn group_name(gid: u32) -> String {
+    static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    let groups = GROUPS.get_or_init(|| load("/etc/group"));
+    groups.get(&gid).cloned().unwrap_or_else(|| gid.to_string())
+}
@@ -0,0 +1,232 @@
+// This file defines how the entries of a directory are ordered.
+
+use std::cmp::Ordering;
+
+use clap::ValueEnum;
+
+use super::tree::FileNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortKey {
+    /// Case-insensitive natural order (file2 before file10)
+    #[default]
+    Name,
+    /// File size, largest first
+    Size,
+    /// Modification time, newest first
+    Mtime,
+    /// Extension, then name
+    Ext,
+    /// Disk usage, largest first (directories once their size is computed)
+    Usage,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Size,
+            SortKey::Size => SortKey::Mtime,
+            SortKey::Mtime => SortKey::Ext,
+            SortKey::Ext => SortKey::Usage,
+            SortKey::Usage => SortKey::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Mtime => "mtime",
+            SortKey::Ext => "ext",
+            SortKey::Usage => "disk usage",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortMode {
+    pub key: SortKey,
+    pub dirs_first: bool,
+    pub reverse: bool,
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Name,
+            dirs_first: true,
+            reverse: false,
+        }
+    }
+}
+
+impl SortMode {
+    pub fn label(&self) -> String {
+        let mut out = format!("sort: {}", self.key.label());
+        if self.reverse {
+            out.push_str(" (reversed)");
+        }
+        if self.dirs_first {
+            out.push_str(", dirs first");
+        }
+        out
+    }
+
+    // Directories stay on top regardless of `reverse`; only the key order flips.
+    pub fn compare(&self, a: &FileNode, b: &FileNode) -> Ordering {
+        if self.dirs_first && a.is_dir != b.is_dir {
+            return b.is_dir.cmp(&a.is_dir);
+        }
+        let by_key = match self.key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => b.size.cmp(&a.size),
+            SortKey::Mtime => b.modified.cmp(&a.modified),
+            SortKey::Ext => extension(&a.name).cmp(&extension(&b.name)),
+            SortKey::Usage => b.disk_usage().cmp(&a.disk_usage()),
+        };
+        let ord = by_key.then_with(|| natural_cmp(&a.name, &b.name));
+        if self.reverse { ord.reverse() } else { ord }
+    }
+}
+
+pub fn sort_nodes(nodes: &mut [FileNode], mode: &SortMode) {
+    nodes.sort_by(|a, b| mode.compare(a, b));
+}
+
+// Re-sort every loaded level, e.g. after the sort mode changed.
+pub fn sort_tree(nodes: &mut [FileNode], mode: &SortMode) {
+    sort_nodes(nodes, mode);
+    for node in nodes.iter_mut() {
+        sort_tree(&mut node.children, mode);
+    }
+}
+
+fn extension(name: &str) -> String {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name[i + 1..].to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+// Case-insensitive comparison that treats runs of digits as numbers, so "file2"
+// sorts before "file10". Falls back to a plain comparison to keep the order total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut ai = a.chars().peekable();
+    let mut bi = b.chars().peekable();
+    loop {
+        match (ai.peek().copied(), bi.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let xs = take_digits(&mut ai);
+                let ys = take_digits(&mut bi);
+                // compare by magnitude first, ignoring leading zeros
+                let (xt, yt) = (xs.trim_start_matches('0'), ys.trim_start_matches('0'));
+                let ord = xt.len().cmp(&yt.len()).then_with(|| xt.cmp(yt));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                ai.next();
+                bi.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut out = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        out.push(c);
+        chars.next();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn node(name: &str, is_dir: bool, size: u64) -> FileNode {
+        let mut node = FileNode::new(name.to_string(), PathBuf::from("/x").join(name), is_dir);
+        node.size = size;
+        node
+    }
+
+    fn sorted(mut names: Vec<&str>) -> Vec<&str> {
+        names.sort_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    fn order(nodes: &mut [FileNode], mode: SortMode) -> Vec<&str> {
+        sort_nodes(nodes, &mode);
+        nodes.iter().map(|n| n.name.as_str()).collect()
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        assert_eq!(
+            sorted(vec!["file10", "file2", "file1", "file100"]),
+            ["file1", "file2", "file10", "file100"]
+        );
+        assert_eq!(
+            sorted(vec!["v1.10", "v1.9", "v1.2"]),
+            ["v1.2", "v1.9", "v1.10"]
+        );
+        assert_eq!(sorted(vec!["a10b", "a9", "a9b"]), ["a9", "a9b", "a10b"]);
+    }
+
+    #[test]
+    fn leading_zeros_keep_the_value() {
+        assert_eq!(
+            sorted(vec!["img010", "img9", "img0011"]),
+            ["img9", "img010", "img0011"]
+        );
+        // same value: settled by the plain comparison so the order is total
+        assert_eq!(natural_cmp("file02", "file2"), Ordering::Less);
+        assert_eq!(natural_cmp("file2", "file02"), Ordering::Greater);
+        assert_eq!(natural_cmp("file2", "file2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn case_is_ignored_until_the_tie_break() {
+        assert_eq!(
+            sorted(vec!["banana", "Cherry", "apple", "Apple"]),
+            ["Apple", "apple", "banana", "Cherry"]
+        );
+        assert_eq!(natural_cmp("README", "readme"), Ordering::Less);
+        assert_eq!(natural_cmp("File10", "file9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn folders_first_can_be_turned_off() {
+        let mut nodes = vec![
+            node("b.txt", false, 1),
+            node("c", true, 0),
+            node("a.txt", false, 3),
+            node("d", true, 0),
+        ];
+        let mut mode = SortMode::default();
+        assert_eq!(order(&mut nodes, mode), ["c", "d", "a.txt", "b.txt"]);
+        // folders stay on top when the order is reversed
+        mode.reverse = true;
+        assert_eq!(order(&mut nodes, mode), ["d", "c", "b.txt", "a.txt"]);
+
+        mode.dirs_first = false;
+        assert_eq!(order(&mut nodes, mode), ["d", "c", "b.txt", "a.txt"]);
+        mode.reverse = false;
+        assert_eq!(order(&mut nodes, mode), ["a.txt", "b.txt", "c", "d"]);
+        mode.key = SortKey::Size;
+        assert_eq!(order(&mut nodes, mode), ["a.txt", "b.txt", "c", "d"]);
+        mode.dirs_first = true;
+        assert_eq!(order(&mut nodes, mode), ["c", "d", "a.txt", "b.txt"]);
+        assert_eq!(mode.label(), "sort: size, dirs first");
+    }
+}
@@ -1,15 +1,36 @@
 // This file defines the structure of the folder tree. It represents folders and files, including methods to expand or collapse folders.
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+use super::filter::ListOptions;
+use super::gitignore::IgnoreChain;
+
+// Total size of everything below a directory, while being counted or once done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirSize {
+    pub bytes: u64,
+    pub done: bool,
+}
 
 #[derive(Debug)]
 pub struct FileNode {
     pub name: String,
     pub path: PathBuf,
     pub is_dir: bool,
+    pub size: u64,                    // bytes, as reported when the node was read
+    pub modified: Option<SystemTime>, // None when the platform/fs doesn't report it
+    pub mode: Option<u32>,            // unix file type and permission bits
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub usage: Option<DirSize>, // directories only, once their size was computed
     pub children: Vec<FileNode>,
     pub is_expanded: bool,
+    pub loading: Option<Arc<AtomicBool>>, // cancel flag while children stream in
 }
 
 #[derive(Debug, Clone)]
@@ -19,7 +40,49 @@ pub struct FlatItem {
     pub path: PathBuf,
     pub is_dir: bool,
     pub is_expanded: bool,
-    pub indent: u16, // how deep to indent when rendering
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub usage: Option<DirSize>,
+    pub indent: u16,         // how deep to indent when rendering
+    pub matches: Vec<usize>, // char indices of `name` matched by the fuzzy filter
+    pub placeholder: bool,   // the "loading…" row under a directory being read
+}
+
+impl FlatItem {
+    pub fn new(node: &FileNode, idx_path: Vec<usize>, indent: u16, matches: Vec<usize>) -> Self {
+        Self {
+            idx_path,
+            name: node.name.clone(),
+            path: node.path.clone(),
+            is_dir: node.is_dir,
+            is_expanded: node.is_expanded,
+            size: node.size,
+            modified: node.modified,
+            mode: node.mode,
+            uid: node.uid,
+            gid: node.gid,
+            usage: node.usage,
+            indent,
+            matches,
+            placeholder: false,
+        }
+    }
+
+    // The row shown below `dir` while its entries are still being read.
+    pub fn loading(dir: &FileNode, mut idx_path: Vec<usize>, indent: u16) -> Self {
+        idx_path.push(dir.children.len()); // past the last child, matches no node
+        Self {
+            name: "loading…".to_string(),
+            is_dir: false,
+            is_expanded: false,
+            usage: None,
+            placeholder: true,
+            ..Self::new(dir, idx_path, indent, Vec::new())
+        }
+    }
 }
 
 impl FileNode {
@@ -28,61 +91,199 @@ impl FileNode {
             name,
             path,
             is_dir,
+            size: 0,
+            modified: None,
+            mode: None,
+            uid: None,
+            gid: None,
+            usage: None,
             children: Vec::new(),
             is_expanded: false,
+            loading: None,
+        }
+    }
+
+    // Build a node for `path`, reading its metadata. Symlinks are followed so a link
+    // to a directory behaves like the directory; broken links describe the link.
+    pub fn from_path(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+        let meta = fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path));
+        let mut node = FileNode::new(name, path, false);
+        if let Ok(meta) = meta {
+            node.is_dir = meta.is_dir();
+            node.size = meta.len();
+            node.modified = meta.modified().ok();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                node.mode = Some(meta.mode());
+                node.uid = Some(meta.uid());
+                node.gid = Some(meta.gid());
+            }
+        }
+        node
+    }
+
+    // Bytes used by this entry: a file's size, or a directory's computed total
+    // (0 until it has one).
+    pub fn disk_usage(&self) -> u64 {
+        if self.is_dir {
+            self.usage.map_or(0, |u| u.bytes)
+        } else {
+            self.size
         }
     }
 
-    pub fn expand(&mut self) {
-        if self.is_dir && !self.is_expanded {
-            self.children = Self::read_directory(&self.path);
+    // Read the children right away. A directory still loading in the background is
+    // read again in full, for callers that need its entries now.
+    pub fn expand(&mut self, opts: &ListOptions) {
+        if self.is_dir && (!self.is_expanded || self.loading.is_some()) {
+            self.cancel_loading();
+            self.children = Self::read_directory(&self.path, opts);
             self.is_expanded = true;
         }
     }
 
     pub fn collapse(&mut self) {
         if self.is_expanded {
+            self.cancel_loading();
             self.children.clear();
             self.is_expanded = false;
         }
     }
 
-    pub fn read_directory(path: &Path) -> Vec<FileNode> {
+    pub fn cancel_loading(&mut self) {
+        if let Some(cancel) = self.loading.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn read_directory(path: &Path, opts: &ListOptions) -> Vec<FileNode> {
         let mut nodes = Vec::new();
+        let ignores = opts.ignores_for(path);
         if let Ok(entries) = fs::read_dir(path) {
             for entry in entries.filter_map(Result::ok) {
-                let path = entry.path();
-                let name = path.file_name().unwrap().to_string_lossy().into_owned();
-                let is_dir = path.is_dir();
-                nodes.push(FileNode::new(name, path, is_dir));
+                let node = FileNode::from_path(entry.path());
+                if Self::listed(&node, ignores.as_ref(), opts) {
+                    nodes.push(node);
+                }
             }
         }
+        super::sort::sort_nodes(&mut nodes, &opts.sort);
         nodes
     }
+
+    // Whether a directory read should include `node`.
+    pub fn listed(node: &FileNode, ignores: Option<&IgnoreChain>, opts: &ListOptions) -> bool {
+        let ignored = ignores.is_some_and(|ig| ig.is_ignored(&node.path, node.is_dir));
+        !ignored && opts.includes(&node.path, node.is_dir)
+    }
 }
 
-pub fn flatten(nodes: &[FileNode]) -> Vec<FlatItem> {
-    fn walk(out: &mut Vec<FlatItem>, nodes: &[FileNode], prefix: &[usize], indent: u16) {
-        for (i, node) in nodes.iter().enumerate() {
-            let mut idx_path = prefix.to_vec();
-            idx_path.push(i);
-            // clone idx_path for storing in out while keeping a copy for recursion
-            let idx_for_item = idx_path.clone();
-            out.push(FlatItem {
-                idx_path: idx_for_item,
-                name: node.name.clone(),
-                path: node.path.clone(),
-                is_dir: node.is_dir,
-                is_expanded: node.is_expanded,
-                indent,
-            });
-            if node.is_dir && node.is_expanded {
-                walk(out, &node.children, idx_path.as_slice(), indent + 1);
-            }
+// Re-read the entries of `dir` into `nodes`, keeping directories that were
+// expanded before expanded (and refreshed in turn).
+pub fn reload(dir: &Path, nodes: &mut Vec<FileNode>, opts: &ListOptions) {
+    let mut previous: HashMap<PathBuf, FileNode> =
+        nodes.drain(..).map(|n| (n.path.clone(), n)).collect();
+    let mut fresh = FileNode::read_directory(dir, opts);
+    for node in fresh.iter_mut().filter(|n| n.is_dir) {
+        if let Some(prev) = previous.remove(&node.path)
+            && prev.is_expanded
+        {
+            node.children = prev.children;
+            node.is_expanded = true;
+            let path = node.path.clone();
+            reload(&path, &mut node.children, opts);
+        }
+    }
+    *nodes = fresh;
+}
+
+// Replace `nodes` with a fresh listing of their directory, read without going
+// further down. Entries that are still there keep their children, expansion and
+// computed size.
+pub fn merge(nodes: &mut Vec<FileNode>, mut fresh: Vec<FileNode>, opts: &ListOptions) {
+    let mut previous: HashMap<PathBuf, FileNode> =
+        nodes.drain(..).map(|n| (n.path.clone(), n)).collect();
+    for node in fresh.iter_mut() {
+        if let Some(prev) = previous.remove(&node.path)
+            && prev.is_dir == node.is_dir
+        {
+            node.children = prev.children;
+            node.is_expanded = prev.is_expanded;
+            node.usage = prev.usage;
+            node.loading = prev.loading;
+        }
+    }
+    for gone in previous.values_mut() {
+        gone.cancel_loading();
+    }
+    super::sort::sort_nodes(&mut fresh, &opts.sort); // sizes may order differently
+    *nodes = fresh;
+}
+
+// Expand every directory up to `depth` levels below `nodes`.
+pub fn expand_to_depth(nodes: &mut [FileNode], depth: usize, opts: &ListOptions) {
+    if depth == 0 {
+        return;
+    }
+    for node in nodes.iter_mut().filter(|n| n.is_dir) {
+        node.expand(opts);
+        expand_to_depth(&mut node.children, depth - 1, opts);
+    }
+}
+
+// Expand the chain of directories leading to `target` and return the index path of
+// its node, or None if it isn't below `nodes` (or is filtered out).
+pub fn reveal(nodes: &mut [FileNode], target: &Path, opts: &ListOptions) -> Option<Vec<usize>> {
+    let mut idx_path = Vec::new();
+    let mut level = nodes;
+    loop {
+        let i = level.iter().position(|n| target.starts_with(&n.path))?;
+        idx_path.push(i);
+        let node = &mut level[i];
+        if node.path == target {
+            return Some(idx_path);
+        }
+        node.expand(opts);
+        level = &mut node.children;
+    }
+}
+
+// The node at `idx_path`.
+pub fn node_at<'a>(nodes: &'a [FileNode], idx_path: &[usize]) -> Option<&'a FileNode> {
+    let (first, rest) = idx_path.split_first()?;
+    let node = nodes.get(*first)?;
+    if rest.is_empty() {
+        Some(node)
+    } else {
+        node_at(&node.children, rest)
+    }
+}
+
+// The index path of the loaded node at `path`.
+pub fn index_of(nodes: &[FileNode], path: &Path) -> Option<Vec<usize>> {
+    let mut idx_path = Vec::new();
+    let mut level = nodes;
+    loop {
+        let i = level.iter().position(|n| path.starts_with(&n.path))?;
+        idx_path.push(i);
+        if level[i].path == path {
+            return Some(idx_path);
         }
+        level = &level[i].children;
     }
+}
 
-    let mut out = Vec::new();
-    walk(&mut out, nodes, &[], 0);
-    out
+// The loaded node at `path`, if every directory leading to it is expanded.
+pub fn find_mut<'a>(nodes: &'a mut [FileNode], path: &Path) -> Option<&'a mut FileNode> {
+    let node = nodes.iter_mut().find(|n| path.starts_with(&n.path))?;
+    if node.path == path {
+        Some(node)
+    } else {
+        find_mut(&mut node.children, path)
+    }
 }
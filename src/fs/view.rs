@@ -0,0 +1,192 @@
+// This file keeps the flattened list of rows shown in the tree pane. Rows only hold
+// index paths into the tree; names and metadata are read from the nodes when a row
+// is drawn, so only the rows on screen are materialized. Expanding or collapsing a
+// folder splices its rows in or out instead of flattening the whole tree again.
+
+use std::path::Path;
+
+use super::tree::{self, FileNode, FlatItem};
+
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub idx_path: Vec<usize>, // path of indices from root to the node
+    pub matches: Vec<usize>,  // char indices of the name matched by the fuzzy filter
+    pub placeholder: bool,    // the "loading…" row under a directory being read
+}
+
+#[derive(Debug, Default)]
+pub struct TreeView {
+    rows: Vec<Row>,
+    query: String, // fuzzy filter the rows were built with, empty for none
+}
+
+impl TreeView {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Flatten `nodes` from scratch, keeping only fuzzy matches of `query` (and their
+    // ancestors) when it isn't empty.
+    pub fn rebuild(&mut self, nodes: &[FileNode], query: &str) {
+        self.query = query.to_string();
+        self.rows = if query.is_empty() {
+            let mut rows = Vec::new();
+            walk(&mut rows, nodes, &[]);
+            rows
+        } else {
+            filtered(nodes, query)
+        };
+    }
+
+    // Bring the rows below the directory at `idx_path` up to date after it was
+    // expanded, collapsed or got new children. A filtered view is rebuilt instead,
+    // since matches can show up or go away anywhere below it.
+    pub fn refresh(&mut self, nodes: &[FileNode], idx_path: &[usize]) {
+        if !self.query.is_empty() {
+            let query = std::mem::take(&mut self.query);
+            self.rebuild(nodes, &query);
+            return;
+        }
+        let (Some(at), Some(node)) = (self.find(idx_path), tree::node_at(nodes, idx_path)) else {
+            return;
+        };
+        let end = self.rows[at + 1..]
+            .iter()
+            .position(|r| !r.idx_path.starts_with(idx_path))
+            .map_or(self.rows.len(), |n| at + 1 + n);
+        let mut below = Vec::new();
+        walk_below(&mut below, node, idx_path);
+        self.rows.splice(at + 1..end, below);
+    }
+
+    // The row for the node at `idx_path`, if it is shown.
+    pub fn find(&self, idx_path: &[usize]) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|r| !r.placeholder && r.idx_path == idx_path)
+    }
+
+    // The row for the node at `path`, if it is shown.
+    pub fn position(&self, nodes: &[FileNode], path: &Path) -> Option<usize> {
+        self.find(&tree::index_of(nodes, path)?)
+    }
+
+    // The first row whose own name matched the filter.
+    pub fn first_match(&self) -> Option<usize> {
+        self.rows.iter().position(|r| !r.matches.is_empty())
+    }
+
+    // The node behind row `i`; None for placeholders.
+    pub fn node<'a>(&self, nodes: &'a [FileNode], i: usize) -> Option<&'a FileNode> {
+        let row = self.rows.get(i).filter(|r| !r.placeholder)?;
+        tree::node_at(nodes, &row.idx_path)
+    }
+
+    // Every node shown, in row order.
+    pub fn nodes<'a>(&'a self, nodes: &'a [FileNode]) -> impl Iterator<Item = &'a FileNode> {
+        (0..self.rows.len()).filter_map(move |i| self.node(nodes, i))
+    }
+
+    // Row `i` with everything needed to draw it.
+    pub fn item(&self, nodes: &[FileNode], i: usize) -> Option<FlatItem> {
+        let row = self.rows.get(i)?;
+        let indent = (row.idx_path.len() - 1) as u16;
+        if row.placeholder {
+            let parent = &row.idx_path[..row.idx_path.len() - 1];
+            let dir = tree::node_at(nodes, parent)?;
+            return Some(FlatItem::loading(dir, parent.to_vec(), indent));
+        }
+        let node = tree::node_at(nodes, &row.idx_path)?;
+        Some(FlatItem::new(
+            node,
+            row.idx_path.clone(),
+            indent,
+            row.matches.clone(),
+        ))
+    }
+
+    // Up to `count` rows starting at `first`, for drawing the part that fits on screen.
+    pub fn items(&self, nodes: &[FileNode], first: usize, count: usize) -> Vec<FlatItem> {
+        (first..self.rows.len().min(first.saturating_add(count)))
+            .filter_map(|i| self.item(nodes, i))
+            .collect()
+    }
+}
+
+fn row(idx_path: Vec<usize>) -> Row {
+    Row {
+        idx_path,
+        matches: Vec::new(),
+        placeholder: false,
+    }
+}
+
+fn walk(out: &mut Vec<Row>, nodes: &[FileNode], prefix: &[usize]) {
+    for (i, node) in nodes.iter().enumerate() {
+        let mut idx_path = prefix.to_vec();
+        idx_path.push(i);
+        out.push(row(idx_path.clone()));
+        walk_below(out, node, &idx_path);
+    }
+}
+
+// The rows under `node`: its children when expanded, then the loading row while
+// they are still being read.
+fn walk_below(out: &mut Vec<Row>, node: &FileNode, idx_path: &[usize]) {
+    if node.is_dir && node.is_expanded {
+        walk(out, &node.children, idx_path);
+    }
+    if node.loading.is_some() {
+        let mut idx_path = idx_path.to_vec();
+        idx_path.push(node.children.len()); // past the last child, matches no node
+        out.push(Row {
+            placeholder: true,
+            ..row(idx_path)
+        });
+    }
+}
+
+// Only nodes whose name fuzzy-matches `query` plus their ancestors, so the tree
+// shape is preserved. Siblings are ranked by the best score found in their subtree.
+fn filtered(nodes: &[FileNode], query: &str) -> Vec<Row> {
+    // Returns one (best score, rows) group per surviving node at this level.
+    fn walk(nodes: &[FileNode], prefix: &[usize], query: &str) -> Vec<(i64, Vec<Row>)> {
+        let mut groups = Vec::new();
+        for (i, node) in nodes.iter().enumerate() {
+            let mut idx_path = prefix.to_vec();
+            idx_path.push(i);
+
+            let own = crate::fuzzy::score(query, &node.name);
+            let children = if node.is_dir && node.is_expanded {
+                walk(&node.children, &idx_path, query)
+            } else {
+                Vec::new()
+            };
+            let best = own
+                .as_ref()
+                .map(|(s, _)| *s)
+                .into_iter()
+                .chain(children.iter().map(|(s, _)| *s))
+                .max();
+            let Some(best) = best else {
+                continue; // neither this node nor anything below it matches
+            };
+
+            let matches = own.map(|(_, m)| m).unwrap_or_default();
+            let mut rows = vec![Row {
+                matches,
+                ..row(idx_path)
+            }];
+            rows.extend(children.into_iter().flat_map(|(_, r)| r));
+            groups.push((best, rows));
+        }
+        // stable sort keeps directory order for equal scores
+        groups.sort_by_key(|g| std::cmp::Reverse(g.0));
+        groups
+    }
+
+    walk(nodes, &[], query)
+        .into_iter()
+        .flat_map(|(_, rows)| rows)
+        .collect()
+}
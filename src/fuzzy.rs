@@ -0,0 +1,175 @@
+// This file implements the fuzzy matching used to filter and rank entries by name.
+
+// Query line state for the `/` filter.
+pub struct FuzzyFilter {
+    pub query: String,
+    pub editing: bool, // typing goes to the query instead of commands
+}
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 15;
+const BOUNDARY: i64 = 10;
+const GAP: i64 = 1;
+const MAX_GAP_PENALTY: i64 = 10;
+
+// Score `text` against `query` as a subsequence match. Returns the score (higher is
+// better) and the char indices of `text` that matched, or None if it doesn't match.
+// Matching is case-insensitive unless the query contains an uppercase letter.
+pub fn score(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let smart_case = query.chars().any(char::is_uppercase);
+    // Unicode-aware so that "é" finds "É"; the rare letters that lower to several
+    // chars keep the first, which still lines up one to one with `text`
+    let fold = |c: char| {
+        if smart_case {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let text: Vec<char> = text.chars().collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // Try every occurrence of the first query char as a starting point and keep the
+    // best greedy alignment; names are short, so this stays cheap.
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in 0..text.len() {
+        if fold(text[start]) != query[0] {
+            continue;
+        }
+        let Some(positions) = align(&query, &text, start, fold) else {
+            break; // later starts can only match less
+        };
+        let s = rate(&positions, &text);
+        if best.as_ref().is_none_or(|(b, _)| s > *b) {
+            best = Some((s, positions));
+        }
+    }
+    best
+}
+
+fn align(
+    query: &[char],
+    text: &[char],
+    start: usize,
+    fold: impl Fn(char) -> char,
+) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut ti = start;
+    for &qc in query {
+        while ti < text.len() && fold(text[ti]) != qc {
+            ti += 1;
+        }
+        if ti == text.len() {
+            return None;
+        }
+        positions.push(ti);
+        ti += 1;
+    }
+    Some(positions)
+}
+
+fn rate(positions: &[usize], text: &[char]) -> i64 {
+    let mut score = 0;
+    let mut prev: Option<usize> = None;
+    for &p in positions {
+        score += MATCH;
+        if is_boundary(text, p) {
+            score += BOUNDARY;
+        }
+        match prev {
+            Some(q) if p == q + 1 => score += CONSECUTIVE,
+            Some(q) => score -= ((p - q - 1) as i64 * GAP).min(MAX_GAP_PENALTY),
+            None => score -= (p as i64 * GAP).min(MAX_GAP_PENALTY),
+        }
+        prev = Some(p);
+    }
+    // prefer shorter names when everything else is equal
+    score - (text.len() as i64 / 8)
+}
+
+fn is_boundary(text: &[char], i: usize) -> bool {
+    if i == 0 {
+        return true;
+    }
+    let (prev, cur) = (text[i - 1], text[i]);
+    matches!(prev, '_' | '-' | '.' | ' ' | '/') || (prev.is_lowercase() && cur.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(query: &str, text: &str) -> Option<Vec<usize>> {
+        score(query, text).map(|(_, positions)| positions)
+    }
+
+    // `names` that match `query`, best first, ties kept in the given order.
+    fn ranked<'a>(query: &str, names: &[&'a str]) -> Vec<&'a str> {
+        let mut hits: Vec<(i64, &str)> = names
+            .iter()
+            .filter_map(|name| Some((score(query, name)?.0, *name)))
+            .collect();
+        hits.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+        hits.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn matches_subsequences() {
+        assert_eq!(matches("", "anything"), Some(vec![]));
+        assert_eq!(matches("mrs", "main.rs"), Some(vec![0, 5, 6]));
+        assert_eq!(matches("abc", "a_b_c"), Some(vec![0, 2, 4]));
+        assert_eq!(matches("cba", "abc"), None);
+        assert_eq!(matches("abcd", "abc"), None);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(matches("readme", "README.md").is_some());
+        assert!(matches("Readme", "README.md").is_none());
+        assert!(matches("README", "README.md").is_some());
+        assert!(matches("Main", "main.rs").is_none());
+    }
+
+    #[test]
+    fn smart_case_folds_beyond_ascii() {
+        assert_eq!(matches("é", "Été"), Some(vec![0]));
+        assert_eq!(matches("été", "ÉTÉ.txt"), Some(vec![0, 1, 2]));
+        assert_eq!(matches("straße", "STRAßE"), Some(vec![0, 1, 2, 3, 4, 5]));
+        // an uppercase letter anywhere makes the whole query exact
+        assert!(matches("É", "été").is_none());
+        assert!(matches("É", "Été").is_some());
+    }
+
+    #[test]
+    fn ranks_tighter_and_earlier_matches_first() {
+        // consecutive beats scattered
+        assert_eq!(
+            ranked("main", &["m_a_i_n.rs", "main.rs"]),
+            ["main.rs", "m_a_i_n.rs"]
+        );
+        // word starts beat the middle of a word
+        assert_eq!(ranked("fb", &["afxb", "foo_bar"]), ["foo_bar", "afxb"]);
+        assert_eq!(ranked("fb", &["sofab", "fooBar"]), ["fooBar", "sofab"]);
+        // matches nearer the start beat later ones
+        assert_eq!(
+            ranked("log", &["backup.log", "log.txt"]),
+            ["log.txt", "backup.log"]
+        );
+        // and all else equal, shorter names come first
+        assert_eq!(
+            ranked("notes", &["notes-from-the-meeting.md", "notes.md"]),
+            ["notes.md", "notes-from-the-meeting.md"]
+        );
+        assert_eq!(ranked("xyz", &["abc", "def"]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn picks_the_best_of_several_alignments() {
+        // the first "s" would give a scattered match; the later one is consecutive
+        let (_, positions) = score("src", "usr/src").unwrap();
+        assert_eq!(positions, [4, 5, 6]);
+    }
+}
@@ -0,0 +1,414 @@
+// This file implements the full-screen hex viewer. Only the rows on screen are held
+// in memory: every scroll seeks and reads just that window, and searches stream
+// through the file in chunks on a worker thread, so files of any size can be
+// inspected without the viewer locking up.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+use crate::tasks::TaskResult;
+
+pub const ROW: u64 = 16; // bytes per row
+const CHUNK: u64 = 64 * 1024; // read size while searching
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Goto,
+    Search,
+}
+
+// The outcome of a search, sent back by its worker.
+pub struct Found {
+    result: io::Result<Option<u64>>,
+    cancel: Arc<AtomicBool>, // identifies the search it belongs to
+}
+
+pub struct HexInput {
+    pub prompt: Prompt,
+    pub text: String,
+}
+
+pub struct HexView {
+    pub path: PathBuf,
+    pub size: u64,
+    pub top: u64,      // offset of the first row on screen, a multiple of ROW
+    pub cursor: u64,   // offset of the selected byte
+    pub rows: u64,     // rows that fit on screen
+    pub data: Vec<u8>, // bytes from `top` for the rows on screen
+    pub input: Option<HexInput>,
+    pub pattern: Option<Vec<u8>>, // last searched bytes
+    pub found: Option<u64>,       // offset of the highlighted match
+    file: File,
+    loaded: Option<(u64, u64)>, // (top, rows) that `data` was read for
+    searching: Option<Arc<AtomicBool>>, // cancel flag of the running search
+}
+
+impl HexView {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            size,
+            top: 0,
+            cursor: 0,
+            rows: 0,
+            data: Vec::new(),
+            input: None,
+            pattern: None,
+            found: None,
+            file,
+            loaded: None,
+            searching: None,
+        })
+    }
+
+    // Read the window for `rows` screen rows, unless it is already loaded.
+    pub fn fill(&mut self, rows: u64) -> io::Result<()> {
+        self.rows = rows.max(1);
+        self.scroll_to_cursor();
+        if self.loaded == Some((self.top, self.rows)) {
+            return Ok(());
+        }
+        self.loaded = None;
+        self.data = read_at(&mut self.file, self.top, self.rows * ROW)?;
+        self.loaded = Some((self.top, self.rows));
+        Ok(())
+    }
+
+    // Move the cursor by a signed number of bytes, clamped to the file.
+    pub fn move_cursor(&mut self, delta: i64) {
+        let last = self.size.saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn page(&mut self, pages: i64) {
+        self.move_cursor(pages * self.rows.max(1) as i64 * ROW as i64);
+    }
+
+    pub fn goto(&mut self, offset: u64) {
+        self.cursor = offset.min(self.size.saturating_sub(1));
+        // put the target row at the top of the screen
+        self.top = self.cursor - self.cursor % ROW;
+        self.scroll_to_cursor();
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let row = self.cursor - self.cursor % ROW;
+        let span = self.rows.max(1) * ROW;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + span {
+            self.top = row + ROW - span;
+        }
+        // don't leave blank rows below the end of the file when it can fill the screen
+        let end = self.size.div_ceil(ROW) * ROW;
+        self.top = self.top.min(end.saturating_sub(span));
+    }
+
+    // Look for the next (or previous) match of the last pattern on a worker,
+    // wrapping around. Replaces a search that is still running. Returns false when
+    // there is no pattern yet.
+    pub fn find_next(&mut self, backward: bool, tx: Sender<TaskResult>) -> bool {
+        let Some(pattern) = self.pattern.clone() else {
+            return false;
+        };
+        self.cancel_search();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.searching = Some(cancel.clone());
+        let (path, cursor, size) = (self.path.clone(), self.cursor, self.size);
+        thread::spawn(move || {
+            let result = File::open(&path).and_then(|mut file| {
+                let file = &mut file;
+                if backward {
+                    match rfind(file, &pattern, cursor, &cancel)? {
+                        Some(at) => Ok(Some(at)),
+                        None => rfind(file, &pattern, size, &cancel),
+                    }
+                } else {
+                    match find(file, &pattern, cursor + 1, size, &cancel)? {
+                        Some(at) => Ok(Some(at)),
+                        None => find(file, &pattern, 0, cursor + 1, &cancel),
+                    }
+                }
+            });
+            let _ = tx.send(TaskResult::HexFound(Found { result, cancel }));
+        });
+        true
+    }
+
+    pub fn searching(&self) -> bool {
+        self.searching.is_some()
+    }
+
+    // Stop the running search, if any. Returns whether there was one.
+    pub fn cancel_search(&mut self) -> bool {
+        let Some(cancel) = self.searching.take() else {
+            return false;
+        };
+        cancel.store(true, Ordering::Relaxed);
+        true
+    }
+
+    // Take in the outcome of a search and jump to the match. Returns None for a
+    // search that was canceled or replaced meanwhile.
+    pub fn receive(&mut self, found: Found) -> Option<io::Result<Option<u64>>> {
+        if !self
+            .searching
+            .as_ref()
+            .is_some_and(|c| Arc::ptr_eq(c, &found.cancel))
+        {
+            return None;
+        }
+        self.searching = None;
+        if let Ok(hit) = found.result {
+            self.found = hit;
+            if let Some(at) = hit {
+                self.goto(at);
+            }
+        }
+        Some(found.result)
+    }
+}
+
+impl Drop for HexView {
+    fn drop(&mut self) {
+        self.cancel_search();
+    }
+}
+
+// First match starting in [start, end), checking for cancellation between chunks.
+fn find(
+    file: &mut File,
+    pattern: &[u8],
+    start: u64,
+    end: u64,
+    cancel: &AtomicBool,
+) -> io::Result<Option<u64>> {
+    let overlap = pattern.len() as u64 - 1;
+    let mut pos = start;
+    while pos < end {
+        check(cancel)?;
+        let buf = read_at(file, pos, CHUNK + overlap)?;
+        if let Some(i) = buf.windows(pattern.len()).position(|w| w == pattern)
+            && pos + (i as u64) < end
+        {
+            return Ok(Some(pos + i as u64));
+        }
+        if (buf.len() as u64) < CHUNK + overlap {
+            break; // reached the end of the file
+        }
+        pos += CHUNK;
+    }
+    Ok(None)
+}
+
+// Last match starting before `before`.
+fn rfind(
+    file: &mut File,
+    pattern: &[u8],
+    before: u64,
+    cancel: &AtomicBool,
+) -> io::Result<Option<u64>> {
+    let overlap = pattern.len() as u64 - 1;
+    let mut end = before;
+    while end > 0 {
+        check(cancel)?;
+        let start = end.saturating_sub(CHUNK);
+        let buf = read_at(file, start, end - start + overlap)?;
+        if let Some(i) = buf
+            .windows(pattern.len())
+            .rposition(|w| w == pattern)
+            .filter(|&i| start + (i as u64) < end)
+        {
+            return Ok(Some(start + i as u64));
+        }
+        end = start;
+    }
+    Ok(None)
+}
+
+fn check(cancel: &AtomicBool) -> io::Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "canceled"));
+    }
+    Ok(())
+}
+
+fn read_at(file: &mut File, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    file.take(len).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+// An offset typed at the goto prompt: decimal, "0x" hex, or relative with +/-.
+pub fn parse_offset(text: &str, current: u64) -> Option<u64> {
+    let text = text.trim();
+    let (sign, rest) = match text.chars().next()? {
+        '+' => (1, &text[1..]),
+        '-' => (-1, &text[1..]),
+        _ => (0, text),
+    };
+    let rest = rest.trim();
+    let value = match rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => rest.parse().ok()?,
+    };
+    Some(match sign {
+        1 => current.saturating_add(value),
+        -1 => current.saturating_sub(value),
+        _ => value,
+    })
+}
+
+// A search pattern: hex bytes like "7f 45 4c 46" or "deadbeef", or text. A leading
+// quote forces text, e.g. "\"cafe" searches for the characters, not 0xca 0xfe.
+pub fn parse_pattern(text: &str) -> Option<Vec<u8>> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let quoted = quoted.strip_suffix('"').unwrap_or(quoted);
+        return (!quoted.is_empty()).then(|| quoted.as_bytes().to_vec());
+    }
+    let digits: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let is_hex = !digits.is_empty()
+        && digits.len().is_multiple_of(2)
+        && digits.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex {
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+            .collect()
+    } else {
+        (!text.is_empty()).then(|| text.as_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A scratch file for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "file-picker-hexview-{}-{}",
+                name,
+                std::process::id()
+            ));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+
+        fn open(&self) -> File {
+            File::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("1234", 7), Some(1234));
+        assert_eq!(parse_offset(" 0x1f ", 7), Some(0x1f));
+        assert_eq!(parse_offset("0XFF", 7), Some(0xff));
+        assert_eq!(parse_offset("+16", 100), Some(116));
+        assert_eq!(parse_offset("- 0x10", 100), Some(84));
+        assert_eq!(parse_offset("-200", 100), Some(0));
+        assert_eq!(parse_offset("+1", u64::MAX), Some(u64::MAX));
+        for bad in ["", "  ", "+", "0x", "12ab", "0xfg", "ten"] {
+            assert_eq!(parse_offset(bad, 7), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(
+            parse_pattern("7f 45 4c 46"),
+            Some(vec![0x7f, b'E', b'L', b'F'])
+        );
+        assert_eq!(
+            parse_pattern("DEADbeef"),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        // a quote forces text, with or without the closing one
+        assert_eq!(parse_pattern("\"cafe\""), Some(b"cafe".to_vec()));
+        assert_eq!(parse_pattern("\"cafe"), Some(b"cafe".to_vec()));
+        assert_eq!(parse_pattern("cafe"), Some(vec![0xca, 0xfe]));
+        // anything that isn't whole hex bytes is text
+        assert_eq!(parse_pattern("abc"), Some(b"abc".to_vec()));
+        assert_eq!(parse_pattern("hello world"), Some(b"hello world".to_vec()));
+        assert_eq!(parse_pattern("ü"), Some("ü".as_bytes().to_vec()));
+        assert_eq!(parse_pattern(""), None);
+        assert_eq!(parse_pattern("\""), None);
+        assert_eq!(parse_pattern("\"\""), None);
+    }
+
+    // Three chunks of zeros with "ABCD" straddling the end of the first chunk and
+    // again the end of the second.
+    fn straddling() -> (Vec<u8>, u64, u64) {
+        let mut data = vec![0; 3 * CHUNK as usize];
+        let (first, second) = (CHUNK - 2, 2 * CHUNK - 1);
+        for at in [first, second] {
+            data[at as usize..at as usize + 4].copy_from_slice(b"ABCD");
+        }
+        (data, first, second)
+    }
+
+    #[test]
+    fn find_across_chunks() {
+        let (data, first, second) = straddling();
+        let scratch = Scratch::new("find", &data);
+        let mut file = scratch.open();
+        let size = data.len() as u64;
+        let cancel = AtomicBool::new(false);
+        let mut find = |start, end| find(&mut file, b"ABCD", start, end, &cancel).unwrap();
+        assert_eq!(find(0, size), Some(first));
+        assert_eq!(find(first, size), Some(first));
+        assert_eq!(find(first + 1, size), Some(second));
+        assert_eq!(find(CHUNK, size), Some(second));
+        assert_eq!(find(second + 1, size), None);
+        // a match must start before `end`, though it may run past it
+        assert_eq!(find(0, first), None);
+        assert_eq!(find(0, first + 1), Some(first));
+    }
+
+    #[test]
+    fn rfind_across_chunks() {
+        let (data, first, second) = straddling();
+        let scratch = Scratch::new("rfind", &data);
+        let mut file = scratch.open();
+        let size = data.len() as u64;
+        let cancel = AtomicBool::new(false);
+        let mut rfind = |before| rfind(&mut file, b"ABCD", before, &cancel).unwrap();
+        assert_eq!(rfind(size), Some(second));
+        assert_eq!(rfind(second + 1), Some(second));
+        assert_eq!(rfind(second), Some(first));
+        assert_eq!(rfind(CHUNK), Some(first));
+        assert_eq!(rfind(first + 1), Some(first));
+        assert_eq!(rfind(first), None);
+    }
+
+    #[test]
+    fn searches_stop_when_canceled() {
+        let (data, _, _) = straddling();
+        let scratch = Scratch::new("cancel", &data);
+        let mut file = scratch.open();
+        let cancel = AtomicBool::new(true);
+        let err = find(&mut file, b"ABCD", 0, data.len() as u64, &cancel).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+        let err = rfind(&mut file, b"ABCD", data.len() as u64, &cancel).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+}
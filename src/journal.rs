@@ -0,0 +1,630 @@
+// This file keeps the undo/redo journal of file operations. Every rename, move,
+// copy, new entry and trash done from the picker is recorded as a list of steps,
+// and the journal is written to a state file after each change so it survives a
+// crash or a restart.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+
+use crate::transfer;
+use crate::trash;
+
+const KEEP: usize = 100; // operations remembered for undo
+
+// One entry changed on disk; an operation over several entries has several steps.
+#[derive(Debug, Clone)]
+pub enum Step {
+    Moved { from: PathBuf, to: PathBuf }, // renames too
+    Copied { from: PathBuf, to: PathBuf },
+    Created { path: PathBuf, is_dir: bool },
+    Trashed { original: PathBuf, file: PathBuf }, // `file` is where it sits in the trash
+}
+
+impl Step {
+    // The copy or move that undoing (or redoing) the step takes, as whether it
+    // moves, from where and to where. None for steps that are replayed in place.
+    pub fn transfer(&self, undo: bool) -> Option<(bool, &Path, &Path)> {
+        match (self, undo) {
+            (Step::Moved { from, to }, true) => Some((true, to, from)),
+            (Step::Moved { from, to }, false) => Some((true, from, to)),
+            (Step::Copied { from, to }, false) => Some((false, from, to)),
+            _ => None,
+        }
+    }
+
+    // The step once its transfer put the entry at `placed`, which is not where it
+    // was recorded when a conflict was settled by renaming.
+    pub fn placed(&self, undo: bool, placed: PathBuf) -> Step {
+        match (self, undo) {
+            (Step::Moved { to, .. }, true) => Step::Moved {
+                from: placed,
+                to: to.clone(),
+            },
+            (Step::Moved { from, .. }, false) => Step::Moved {
+                from: from.clone(),
+                to: placed,
+            },
+            (Step::Copied { from, .. }, false) => Step::Copied {
+                from: from.clone(),
+                to: placed,
+            },
+            _ => self.clone(),
+        }
+    }
+
+    // Undo (or redo) a step that isn't a transfer. Returns the step to keep for
+    // going the other way.
+    pub fn replay(&self, undo: bool) -> io::Result<Step> {
+        if self.transfer(undo).is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "copies and moves run as transfers",
+            ));
+        }
+        if undo { self.undo() } else { self.redo() }
+    }
+
+    fn undo(&self) -> io::Result<Step> {
+        match self {
+            Step::Moved { .. } => unreachable!("moves run as transfers"),
+            Step::Copied { to: path, .. } | Step::Created { path, .. } => discard(path)?,
+            Step::Trashed { original, file } => {
+                let item = trash::Item::at(file, original).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is no longer in the trash", original.display()),
+                    )
+                })?;
+                trash::restore(&item)?;
+            }
+        }
+        Ok(self.clone())
+    }
+
+    fn redo(&self) -> io::Result<Step> {
+        match self {
+            Step::Moved { .. } | Step::Copied { .. } => {
+                unreachable!("copies and moves run as transfers")
+            }
+            Step::Created { path, is_dir: true } => fs::create_dir(path)?,
+            Step::Created {
+                path,
+                is_dir: false,
+            } => {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)?;
+            }
+            Step::Trashed { original, .. } => {
+                // it lands under a new name in the trash this time
+                let file = trash::trash(original)?;
+                return Ok(Step::Trashed {
+                    original: original.clone(),
+                    file,
+                });
+            }
+        }
+        Ok(self.clone())
+    }
+
+    // The entry to select once the step was undone or redone.
+    fn shown(&self, undone: bool) -> Option<&Path> {
+        match (self, undone) {
+            (Step::Moved { from, .. } | Step::Copied { from, .. }, true) => Some(from),
+            (Step::Moved { to, .. } | Step::Copied { to, .. }, false) => Some(to),
+            (Step::Created { path, .. }, false) => Some(path),
+            (Step::Created { .. }, true) => None,
+            (Step::Trashed { original, .. }, true) => Some(original),
+            (Step::Trashed { .. }, false) => None,
+        }
+    }
+
+    // Directories whose listings the step changes.
+    fn dirs(&self) -> Vec<PathBuf> {
+        let paths: Vec<&Path> = match self {
+            Step::Moved { from, to } => vec![from, to],
+            Step::Copied { to, .. } => vec![to],
+            Step::Created { path, .. } => vec![path],
+            Step::Trashed { original, .. } => vec![original],
+        };
+        paths
+            .into_iter()
+            .filter_map(|p| p.parent().map(Path::to_path_buf))
+            .collect()
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Step::Moved { from, to } => {
+                json!({"op": "move", "from": path_json(from), "to": path_json(to)})
+            }
+            Step::Copied { from, to } => {
+                json!({"op": "copy", "from": path_json(from), "to": path_json(to)})
+            }
+            Step::Created { path, is_dir } => {
+                json!({"op": "create", "path": path_json(path), "is_dir": is_dir})
+            }
+            Step::Trashed { original, file } => json!({
+                "op": "trash",
+                "original": path_json(original),
+                "file": path_json(file),
+            }),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Step> {
+        let path = |key: &str| path_from_json(value.get(key)?);
+        Some(match value.get("op")?.as_str()? {
+            "move" => Step::Moved {
+                from: path("from")?,
+                to: path("to")?,
+            },
+            "copy" => Step::Copied {
+                from: path("from")?,
+                to: path("to")?,
+            },
+            "create" => Step::Created {
+                path: path("path")?,
+                is_dir: value.get("is_dir")?.as_bool()?,
+            },
+            "trash" => Step::Trashed {
+                original: path("original")?,
+                file: path("file")?,
+            },
+            _ => return None,
+        })
+    }
+}
+
+// A recorded operation: the status message it finished with and its steps, in
+// the order they were done.
+#[derive(Debug)]
+struct Entry {
+    label: String,
+    steps: Vec<Step>,
+}
+
+// What undoing or redoing an operation did.
+pub struct Outcome {
+    pub label: String,
+    pub dirs: Vec<PathBuf>,      // directories to re-read
+    pub select: Option<PathBuf>, // entry to reveal
+    pub result: io::Result<()>,
+}
+
+#[derive(Default)]
+pub struct Journal {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    file: Option<PathBuf>, // None when there is nowhere to keep state
+}
+
+impl Journal {
+    // The journal left by the last session, or an empty one.
+    pub fn load() -> Self {
+        Self::open(state_dir().map(|dir| dir.join("file-picker").join("journal.json")))
+    }
+
+    // The journal kept in `file`, or an empty one when it can't be read.
+    fn open(file: Option<PathBuf>) -> Self {
+        let value: Value = file
+            .as_ref()
+            .and_then(|f| fs::read_to_string(f).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        let entries = |key: &str| -> Vec<Entry> {
+            let Some(list) = value.get(key).and_then(Value::as_array) else {
+                return Vec::new();
+            };
+            list.iter()
+                .filter_map(|e| {
+                    let steps = e.get("steps")?.as_array()?;
+                    Some(Entry {
+                        label: e.get("label")?.as_str()?.to_string(),
+                        steps: steps.iter().map(Step::from_json).collect::<Option<_>>()?,
+                    })
+                })
+                .collect()
+        };
+        Self {
+            undo: entries("undo"),
+            redo: entries("redo"),
+            file,
+        }
+    }
+
+    // Remember an operation that went (at least partly) through. Anything undone
+    // before can't be redone after it.
+    pub fn record(&mut self, label: String, steps: Vec<Step>) {
+        if steps.is_empty() {
+            return;
+        }
+        self.undo.push(Entry { label, steps });
+        if self.undo.len() > KEEP {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.save();
+    }
+
+    // Take the last operation off the undo (or redo) stack to replay it on a
+    // transfer worker. Its steps come in the order they are replayed in: last first
+    // when undoing. None when there is nothing to take.
+    pub fn take(&mut self, undo: bool) -> Option<(String, Vec<Step>)> {
+        let entry = if undo {
+            self.undo.pop()
+        } else {
+            self.redo.pop()
+        }?;
+        let mut steps = entry.steps;
+        if undo {
+            steps.reverse();
+        }
+        Some((entry.label, steps))
+    }
+
+    // Put back an operation the worker replayed: the part that went through goes
+    // on the other stack, the part that didn't (failed, skipped or never reached)
+    // back on the stack it came from.
+    pub fn settle(&mut self, undo: bool, label: String, done: transfer::Done) -> Outcome {
+        let (mut replayed, mut left) = (done.steps, done.left);
+        let mut dirs: Vec<PathBuf> = replayed.iter().chain(&left).flat_map(Step::dirs).collect();
+        dirs.sort();
+        dirs.dedup();
+        let select = replayed
+            .iter()
+            .rev()
+            .find_map(|step| step.shown(undo))
+            .map(Path::to_path_buf);
+        // both parts keep the steps in the order they were first done
+        if undo {
+            replayed.reverse();
+            left.reverse();
+        }
+        let part = |steps: Vec<Step>| {
+            (!steps.is_empty()).then(|| Entry {
+                label: label.clone(),
+                steps,
+            })
+        };
+        let (replayed, left) = (part(replayed), part(left));
+        let (to, from) = if undo {
+            (&mut self.redo, &mut self.undo)
+        } else {
+            (&mut self.undo, &mut self.redo)
+        };
+        to.extend(replayed);
+        from.extend(left);
+        self.save();
+        Outcome {
+            label,
+            dirs,
+            select,
+            result: done.result,
+        }
+    }
+
+    // Write the journal out, replacing the file in one go so a crash halfway leaves
+    // the previous version. Failing to save only costs undo across restarts.
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let entries = |list: &[Entry]| -> Vec<Value> {
+            list.iter()
+                .map(|e| {
+                    json!({
+                        "label": e.label,
+                        "steps": e.steps.iter().map(Step::to_json).collect::<Vec<_>>(),
+                    })
+                })
+                .collect()
+        };
+        let value = json!({"undo": entries(&self.undo), "redo": entries(&self.redo)});
+        let tmp = file.with_extension("json.tmp");
+        let _ = file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, value.to_string()))
+            .and_then(|_| fs::rename(&tmp, file));
+    }
+}
+
+// Take back an entry an undone step created. Empty ones are just removed; anything
+// with content goes to the trash, in case it was worked on since.
+fn discard(path: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    let removed = if meta.is_dir() {
+        fs::remove_dir(path).is_ok() // fails unless empty
+    } else if meta.is_file() && meta.len() == 0 {
+        fs::remove_file(path).is_ok()
+    } else {
+        false
+    };
+    if !removed {
+        trash::trash(path)?;
+    }
+    Ok(())
+}
+
+// Paths are kept as the hex of their bytes: JSON strings must be UTF-8 and file
+// names need not be.
+fn path_json(path: &Path) -> Value {
+    let hex: String = trash::path_bytes(path)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Value::String(hex)
+}
+
+fn path_from_json(value: &Value) -> Option<PathBuf> {
+    let hex = value.as_str()?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(trash::path_from_bytes(bytes))
+}
+
+// $XDG_STATE_HOME, falling back to ~/.local/state.
+fn state_dir() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskResult;
+    use crate::transfer::{Choice, Transfer};
+    use std::sync::mpsc;
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!(
+                "file-picker-journal-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn journal(&self) -> Journal {
+            Journal::open(Some(self.0.join("state").join("journal.json")))
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn created(path: &Path) -> Step {
+        Step::Created {
+            path: path.to_path_buf(),
+            is_dir: false,
+        }
+    }
+
+    // Undo or redo the last entry the way the picker does, on a transfer worker,
+    // settling conflicts with `choice`.
+    fn replay_choosing(journal: &mut Journal, undo: bool, choice: Choice) -> Outcome {
+        let (tx, rx) = mpsc::channel();
+        let (label, steps) = journal.take(undo).unwrap();
+        let mut transfer = Transfer::replay(label.clone(), steps, undo, tx);
+        loop {
+            let TaskResult::Transfer(update) = rx.recv().unwrap() else {
+                continue;
+            };
+            if let Some(done) = transfer.receive(update) {
+                return journal.settle(undo, label, done);
+            }
+            if transfer.conflict.is_some() {
+                transfer.choose(choice);
+            }
+        }
+    }
+
+    fn replay(journal: &mut Journal, undo: bool) -> Outcome {
+        replay_choosing(journal, undo, Choice::Skip)
+    }
+
+    // The steps of each entry on a stack, as the paths they touch.
+    fn stack(entries: &[Entry]) -> Vec<(String, Vec<PathBuf>)> {
+        entries
+            .iter()
+            .map(|e| {
+                let paths = e
+                    .steps
+                    .iter()
+                    .filter_map(|s| s.shown(false))
+                    .map(Path::to_path_buf);
+                (e.label.clone(), paths.collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let scratch = Scratch::new("round");
+        let odd = trash::path_from_bytes(b"/tmp/\xff name\n".to_vec());
+        let mut journal = scratch.journal();
+        journal.record(
+            "Moved 2 entries".to_string(),
+            vec![
+                Step::Moved {
+                    from: PathBuf::from("/a/b"),
+                    to: odd.clone(),
+                },
+                Step::Copied {
+                    from: odd.clone(),
+                    to: PathBuf::from("/c/é"),
+                },
+            ],
+        );
+        journal.record(
+            "Created".to_string(),
+            vec![Step::Created {
+                path: PathBuf::from("/d"),
+                is_dir: true,
+            }],
+        );
+        journal.record(
+            "Trashed".to_string(),
+            vec![Step::Trashed {
+                original: odd.clone(),
+                file: PathBuf::from("/trash/files/name"),
+            }],
+        );
+        // something on the redo stack too
+        let entry = journal.undo.pop().unwrap();
+        journal.redo.push(entry);
+        journal.save();
+
+        let loaded = scratch.journal();
+        assert_eq!(format!("{:?}", loaded.undo), format!("{:?}", journal.undo));
+        assert_eq!(format!("{:?}", loaded.redo), format!("{:?}", journal.redo));
+        assert_eq!(loaded.undo.len(), 2);
+        assert_eq!(loaded.redo.len(), 1);
+        let Step::Trashed { original, .. } = &loaded.redo[0].steps[0] else {
+            panic!("expected a trash step");
+        };
+        assert_eq!(*original, odd);
+    }
+
+    #[test]
+    fn unreadable_file_loads_empty() {
+        let scratch = Scratch::new("unreadable");
+        let file = scratch.0.join("state").join("journal.json");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "not json").unwrap();
+        let journal = scratch.journal();
+        assert!(journal.undo.is_empty() && journal.redo.is_empty());
+    }
+
+    #[test]
+    fn partial_undo_splits_the_entry() {
+        let scratch = Scratch::new("undo");
+        let [a, b, c] = ["a", "b", "c"].map(|n| scratch.0.join(n));
+        for path in [&a, &b, &c] {
+            fs::write(path, "").unwrap();
+        }
+        let mut journal = scratch.journal();
+        journal.record(
+            "Created 3".to_string(),
+            vec![created(&a), created(&b), created(&c)],
+        );
+        // undoing goes c, b, a and stops at b, which is already gone
+        fs::remove_file(&b).unwrap();
+
+        let outcome = replay(&mut journal, true);
+        assert!(outcome.result.is_err());
+        assert!(a.exists() && !c.exists());
+        assert_eq!(
+            stack(&journal.undo),
+            [("Created 3".to_string(), vec![a.clone(), b.clone()])]
+        );
+        assert_eq!(
+            stack(&journal.redo),
+            [("Created 3".to_string(), vec![c.clone()])]
+        );
+
+        // and the split is what gets saved
+        let loaded = scratch.journal();
+        assert_eq!(stack(&loaded.undo), stack(&journal.undo));
+        assert_eq!(stack(&loaded.redo), stack(&journal.redo));
+
+        // redoing the part that was undone brings it back
+        replay(&mut journal, false).result.unwrap();
+        assert!(c.exists());
+        assert_eq!(
+            stack(&journal.undo),
+            [
+                ("Created 3".to_string(), vec![a.clone(), b.clone()]),
+                ("Created 3".to_string(), vec![c.clone()]),
+            ]
+        );
+        assert!(journal.redo.is_empty());
+    }
+
+    #[test]
+    fn partial_redo_splits_the_entry() {
+        let scratch = Scratch::new("redo");
+        let [x, y, z] = ["x", "y", "z"].map(|n| scratch.0.join(n));
+        let mut journal = scratch.journal();
+        journal.redo.push(Entry {
+            label: "Created 3".to_string(),
+            steps: vec![created(&x), created(&y), created(&z)],
+        });
+        // redoing goes x, y, z and stops at y, which can't be created again
+        fs::write(&y, "taken").unwrap();
+
+        let outcome = replay(&mut journal, false);
+        assert!(outcome.result.is_err());
+        assert_eq!(outcome.select, Some(x.clone()));
+        assert!(x.exists() && !z.exists());
+        assert_eq!(fs::read_to_string(&y).unwrap(), "taken");
+        assert_eq!(
+            stack(&journal.undo),
+            [("Created 3".to_string(), vec![x.clone()])]
+        );
+        assert_eq!(
+            stack(&journal.redo),
+            [("Created 3".to_string(), vec![y.clone(), z.clone()])]
+        );
+    }
+
+    #[test]
+    fn move_back_asks_about_conflicts() {
+        let scratch = Scratch::new("conflict");
+        let [a, b] = ["a", "b"].map(|n| scratch.0.join(n));
+        fs::write(&a, "moved").unwrap();
+        fs::rename(&a, &b).unwrap();
+        let mut journal = scratch.journal();
+        let moved = Step::Moved {
+            from: a.clone(),
+            to: b.clone(),
+        };
+        journal.record("Moved".to_string(), vec![moved]);
+        fs::write(&a, "new").unwrap();
+
+        // skipping leaves the step to undo later
+        assert!(replay(&mut journal, true).result.is_ok());
+        assert_eq!(
+            stack(&journal.undo),
+            [("Moved".to_string(), vec![b.clone()])]
+        );
+        assert!(journal.redo.is_empty());
+
+        // renaming puts it next to the new entry, and redo moves it from there
+        let renamed = scratch.0.join("a (2)");
+        let outcome = replay_choosing(&mut journal, true, Choice::Rename);
+        assert_eq!(outcome.select, Some(renamed.clone()));
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "moved");
+        assert_eq!(fs::read_to_string(&a).unwrap(), "new");
+        let Step::Moved { from, to } = &journal.redo[0].steps[0] else {
+            panic!("expected a move");
+        };
+        assert_eq!((from, to), (&renamed, &b));
+
+        replay(&mut journal, false).result.unwrap();
+        assert_eq!(fs::read_to_string(&b).unwrap(), "moved");
+        assert!(!renamed.exists());
+    }
+}
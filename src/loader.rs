@@ -0,0 +1,161 @@
+// This file reads directories on a worker thread when they are expanded, so huge
+// folders and slow mounts don't freeze the interface. Entries are sent back in
+// batches and merged into the tree as they arrive; collapsing the folder cancels
+// the read. Folders that are already loaded are read again the same way when
+// they change, and the fresh listing is merged in one go.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fs::filter::ListOptions;
+use crate::fs::sort;
+use crate::fs::tree::{self, FileNode};
+use crate::tasks::TaskResult;
+
+const BATCH_EVERY: Duration = Duration::from_millis(100);
+
+pub struct Batch {
+    pub dir: PathBuf,
+    nodes: Vec<FileNode>, // sorted within the batch
+    done: bool,
+    cancel: Arc<AtomicBool>, // identifies the read the batch belongs to
+}
+
+// A fresh listing of a loaded folder whose entries changed.
+pub struct Listing {
+    pub dir: PathBuf,
+    nodes: Vec<FileNode>,
+}
+
+// Folders being read again after a change. A folder that changes again while it
+// is being read is read once more when that read comes back, so a burst of changes
+// never has more than one read per folder going.
+#[derive(Default)]
+pub struct Rereads {
+    running: HashSet<PathBuf>,
+    again: HashSet<PathBuf>,
+}
+
+impl Rereads {
+    pub fn start(&mut self, dir: PathBuf, opts: &ListOptions, tx: Sender<TaskResult>) {
+        if self.running.contains(&dir) {
+            self.again.insert(dir);
+            return;
+        }
+        self.running.insert(dir.clone());
+        let opts = opts.clone();
+        thread::spawn(move || {
+            let nodes = FileNode::read_directory(&dir, &opts);
+            let _ = tx.send(TaskResult::Listing(Listing { dir, nodes }));
+        });
+    }
+
+    pub fn busy(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    // Merge a listing into the tree under `root`, and start the next read of its
+    // folder if it changed again meanwhile. Returns the index path of the folder
+    // whose entries were replaced (empty for the root), or None when it isn't
+    // loaded any more.
+    pub fn receive(
+        &mut self,
+        root: &Path,
+        nodes: &mut Vec<FileNode>,
+        listing: Listing,
+        opts: &ListOptions,
+        tx: Sender<TaskResult>,
+    ) -> Option<Vec<usize>> {
+        self.running.remove(&listing.dir);
+        if self.again.remove(&listing.dir) {
+            self.start(listing.dir.clone(), opts, tx);
+        }
+        if listing.dir == root {
+            tree::merge(nodes, listing.nodes, opts);
+            return Some(Vec::new());
+        }
+        let idx_path = tree::index_of(nodes, &listing.dir)?;
+        let node = tree::find_mut(nodes, &listing.dir)?;
+        // a folder collapsed or being expanded meanwhile has nothing to merge into
+        if !node.is_expanded || node.loading.is_some() {
+            return None;
+        }
+        tree::merge(&mut node.children, listing.nodes, opts);
+        Some(idx_path)
+    }
+}
+
+// Mark `node` expanded with no children yet and start reading it in the background.
+pub fn expand(node: &mut FileNode, opts: &ListOptions, tx: Sender<TaskResult>) {
+    if !node.is_dir || node.is_expanded {
+        return;
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    node.children.clear();
+    node.is_expanded = true;
+    node.loading = Some(cancel.clone());
+
+    let (dir, opts) = (node.path.clone(), opts.clone());
+    thread::spawn(move || {
+        let ignores = opts.ignores_for(&dir);
+        let mut nodes = Vec::new();
+        let mut last_sent = Instant::now();
+        let send = |nodes: &mut Vec<FileNode>, done: bool| {
+            let mut nodes = std::mem::take(nodes);
+            sort::sort_nodes(&mut nodes, &opts.sort);
+            let batch = Batch {
+                dir: dir.clone(),
+                nodes,
+                done,
+                cancel: cancel.clone(),
+            };
+            tx.send(TaskResult::DirBatch(batch)).is_ok()
+        };
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.filter_map(Result::ok) {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let node = FileNode::from_path(entry.path());
+                if FileNode::listed(&node, ignores.as_ref(), &opts) {
+                    nodes.push(node);
+                }
+                if last_sent.elapsed() >= BATCH_EVERY {
+                    if !send(&mut nodes, false) {
+                        return;
+                    }
+                    last_sent = Instant::now();
+                }
+            }
+        }
+        send(&mut nodes, true);
+    });
+}
+
+// Merge a batch into the tree. Returns false when the read it came from was
+// canceled or its folder is gone from the tree.
+pub fn receive(nodes: &mut [FileNode], batch: Batch, opts: &ListOptions) -> bool {
+    let Some(node) = tree::find_mut(nodes, &batch.dir) else {
+        return false;
+    };
+    if !node
+        .loading
+        .as_ref()
+        .is_some_and(|c| Arc::ptr_eq(c, &batch.cancel))
+    {
+        return false;
+    }
+    node.children.extend(batch.nodes);
+    // both halves are sorted already, which the merge sort makes cheap
+    sort::sort_nodes(&mut node.children, &opts.sort);
+    if batch.done {
+        node.loading = None;
+    }
+    true
+}
@@ -1,19 +1,39 @@
+use std::collections::BTreeSet;
 use std::env;
-use std::io::stdout;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
 use std::time::{Duration, Instant};
 
-use crossterm::{
-    event::{
-        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
-        MouseEvent, MouseEventKind,
-    },
-    execute,
+use clap::Parser;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
 };
 
+mod cli;
+mod columns;
+mod du;
 mod events;
+mod finder;
 mod fs; // src/fs/mod.rs exposes pub mod icons;
+mod fuzzy;
+mod hexview;
+mod journal;
+mod loader;
+mod ops;
+mod output;
+mod picker;
+mod preview;
+mod tasks;
+mod term;
+mod transfer;
+mod trash;
 mod ui; // new: renderer module
+mod watcher;
+
+const TICK: Duration = Duration::from_millis(50); // background results are picked up this often
+const STATUS_TTL: Duration = Duration::from_secs(5); // status messages go away after this
 
 pub struct Entry {
     // made public so ui.rs can use it
@@ -30,37 +50,310 @@ pub struct App {
     // made public so ui.rs can use it
     pub status: String,
     pub entries: Vec<fs::tree::FileNode>,
+    pub view: fs::view::TreeView, // rows of `entries` shown in the list
     pub selected: Option<usize>,
+    pub offset: usize,                        // first list row on screen
+    pub layout: ui::Layout,                   // where the last frame drew things, for mouse clicks
     pub last_click: Option<(usize, Instant)>, // for double-click detection
     pub open_menu: Option<OpenMenu>,
+    pub prompt: Option<ops::Prompt>, // file operation waiting for a name or a yes/no
+    pub journal: journal::Journal,   // done operations, for undo/redo
+    pub transfer: Option<transfer::Transfer>, // copy or move running in the background
+    pub pick: bool,                  // return the chosen file instead of opening it
+    pub mode: picker::Mode,          // what the picker is allowed to confirm
+    pub save: Option<picker::SaveDialog>, // file name input, only in save mode
+    pub root: PathBuf,
+    pub list: fs::filter::ListOptions, // which entries directory reads include
+    pub marked: BTreeSet<PathBuf>,     // keyed by path so marks survive collapse/expand
+    pub fuzzy: Option<fuzzy::FuzzyFilter>, // active `/` filter, if any
+    pub finder: Option<finder::Finder>, // project-wide finder overlay
+    pub trash: Option<trash::Browser>, // trash browser overlay
+    pub show_preview: bool,
+    pub preview: Option<preview::PreviewCache>, // preview of the selected entry
+    pub focus_preview: bool, // keys browse the preview's key tree instead of the list
+    pub hex: Option<hexview::HexView>, // full-screen hex viewer, replaces the tree
+    pub columns: columns::Columns, // metadata shown next to names
+    pub du: du::DiskUsage,   // directory sizes computed in the background
+    pub tasks: tasks::Tasks, // where background work reports its results
+    pub watcher: watcher::Watcher, // refreshes expanded directories when they change
+    pub rereads: loader::Rereads, // loaded folders being read again after a change
+    pub select_after: Option<PathBuf>, // entry to select once those reads are back
+    pub idle_status: String, // shown once other status messages expire
+    status_since: (String, Instant), // the status last seen and when it was set
 }
 
-fn main() {
-    let mut terminal = ratatui::init();
+impl App {
+    // Row `i` of the list, ready to draw or act on.
+    pub fn item(&self, i: usize) -> Option<fs::tree::FlatItem> {
+        self.view.item(&self.entries, i)
+    }
+
+    pub fn selected_item(&self) -> Option<fs::tree::FlatItem> {
+        self.selected.and_then(|i| self.item(i))
+    }
+
+    // Flatten the tree again after changes other than expanding or collapsing a
+    // single folder, applying the fuzzy filter (if any).
+    pub fn rebuild_view(&mut self) {
+        let query = self.fuzzy.as_ref().map_or("", |f| f.query.as_str());
+        self.view.rebuild(&self.entries, query);
+        self.du.reapply(); // nodes may have been read afresh
+        self.watcher.resync();
+    }
+
+    // Update the rows below the folder at `idx_path` after it was expanded,
+    // collapsed or read again.
+    pub fn refresh_rows(&mut self, idx_path: &[usize]) {
+        self.view.refresh(&self.entries, idx_path);
+        self.watcher.resync();
+    }
+
+    // The preview pane needs room next to the tree; skip it on narrow terminals.
+    pub fn preview_shown(&self, width: u16) -> bool {
+        self.show_preview && width >= 60
+    }
+}
 
-    // Enable mouse capture
-    execute!(stdout(), EnableMouseCapture).expect("failed to enable mouse capture");
+fn main() -> ExitCode {
+    let args = cli::Args::parse();
 
-    let entries = fs::tree::FileNode::read_directory(std::path::Path::new("."));
+    // Work with absolute paths so a picked path is usable from anywhere
+    let root = match std::fs::canonicalize(&args.dir) {
+        Ok(root) if root.is_dir() => root,
+        Ok(_) => {
+            eprintln!("file-picker: {} is not a directory", args.dir.display());
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("file-picker: {}: {}", args.dir.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+    // Checked up front so a mistyped base fails before anything is picked
+    let relative_to = match &args.relative_to {
+        None => None,
+        Some(dir) => match std::fs::canonicalize(dir) {
+            Ok(base) if base.is_dir() => Some(base),
+            Ok(_) => {
+                eprintln!("file-picker: {} is not a directory", dir.display());
+                return ExitCode::from(2);
+            }
+            Err(e) => {
+                eprintln!("file-picker: --relative-to {}: {}", dir.display(), e);
+                return ExitCode::from(2);
+            }
+        },
+    };
+    let mut list = match fs::filter::ListOptions::new(args.hidden, &args.ext, &args.glob) {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("file-picker: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    list.respect_ignore = !args.no_ignore;
+    list.sort = fs::sort::SortMode {
+        key: args.sort,
+        dirs_first: !args.mixed,
+        reverse: args.reverse,
+    };
+    let mut entries = fs::tree::FileNode::read_directory(&root, &list);
+    fs::tree::expand_to_depth(&mut entries, args.depth, &list);
+
+    let mode = if args.directory {
+        picker::Mode::Directory
+    } else if args.save.is_some() {
+        picker::Mode::Save
+    } else {
+        picker::Mode::Open
+    };
+    let status = match mode {
+        picker::Mode::Open => "Ready. Click or scroll. Press q or Esc to quit.",
+        picker::Mode::Directory => {
+            "Pick a directory with Enter, or this one with P. Right/Left expand. Esc cancels."
+        }
+        picker::Mode::Save => {
+            "Type a file name and press Enter to save. Tab copies the selected name."
+        }
+    };
 
+    let tasks = tasks::Tasks::new();
     let mut app = App {
-        status: "Ready. Click or scroll. Press q or Esc to quit.".to_string(),
+        status: status.to_string(),
         entries,
+        view: fs::view::TreeView::default(),
         selected: None,
+        offset: 0,
+        layout: ui::Layout::default(),
         last_click: None,
         open_menu: None,
+        prompt: None,
+        journal: journal::Journal::load(),
+        transfer: None,
+        // the directory and save dialogs only make sense when their answer is printed
+        pick: args.pick || mode != picker::Mode::Open,
+        mode,
+        save: args.save.map(picker::SaveDialog::new),
+        root,
+        list,
+        marked: BTreeSet::new(),
+        fuzzy: None,
+        finder: None,
+        trash: None,
+        show_preview: true,
+        preview: None,
+        focus_preview: false,
+        hex: None,
+        columns: columns::Columns::new(&args.columns),
+        du: du::DiskUsage::new(),
+        watcher: watcher::Watcher::new(tasks.sender()),
+        rereads: loader::Rereads::default(),
+        select_after: None,
+        tasks,
+        idle_status: status.to_string(),
+        status_since: (status.to_string(), Instant::now()),
     };
+    app.columns.absolute_time = args.absolute_time;
+    app.rebuild_view();
     if !app.entries.is_empty() {
         app.selected = Some(0);
     }
+    if let Some(target) = &args.reveal {
+        reveal_path(&mut app, target);
+    }
+
+    let mut terminal = term::init().expect("failed to initialise terminal");
+    let picked = run(&mut terminal, &mut app);
+    term::restore().expect("failed to restore terminal");
+
+    if !app.pick {
+        return ExitCode::SUCCESS;
+    }
+    match picked {
+        Some(paths) => {
+            let opts = output::OutputOptions {
+                format: args.format,
+                print0: args.print0,
+                relative_to,
+            };
+            match output::write_paths(&mut io::stdout().lock(), &paths, &opts) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("file-picker: failed to write output: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        None => ExitCode::FAILURE,
+    }
+}
 
+// Runs the UI until the user quits. Returns the chosen files in picker mode.
+fn run(terminal: &mut term::Tty, app: &mut App) -> Option<Vec<PathBuf>> {
     loop {
+        app.watcher.sync(&app.root, &app.entries);
+        handle_tasks(app);
+        apply_changes(app);
+        if let Some(finder) = app.finder.as_mut() {
+            finder.poll();
+        }
+        if app.du.apply(&mut app.entries) && app.list.sort.key == fs::sort::SortKey::Usage {
+            resort_tree(app);
+        }
+        expire_status(app);
+        refresh_preview(app);
+        if let Some(hex) = app.hex.as_mut() {
+            let height = terminal.size().map(|s| s.height).unwrap_or(0);
+            if let Err(e) = hex.fill(ui::hex_rows(height)) {
+                app.status = format!("Read failed: {}", e);
+            }
+        }
         terminal
-            .draw(|f| ui::draw(f, &app)) // call into ui module
+            .draw(|f| ui::draw(f, app)) // call into ui module
             .expect("failed to draw frame");
 
+        // wait for input, waking up every tick to pick up background results
+        if !event::poll(TICK).unwrap_or(false) {
+            continue;
+        }
+        let ev = event::read().expect("failed to read event");
+
+        if app.hex.is_some() {
+            match ev {
+                Event::Key(k)
+                    if k.kind == KeyEventKind::Press || k.kind == KeyEventKind::Repeat =>
+                {
+                    hex_key(app, k)
+                }
+                Event::Mouse(m) => {
+                    if let Some(hex) = app.hex.as_mut() {
+                        match m.kind {
+                            MouseEventKind::ScrollUp => hex.move_cursor(-3 * hexview::ROW as i64),
+                            MouseEventKind::ScrollDown => hex.move_cursor(3 * hexview::ROW as i64),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if let Some(finder) = app.finder.as_mut() {
+            if let Event::Key(k) = ev
+                && (k.kind == KeyEventKind::Press || k.kind == KeyEventKind::Repeat)
+            {
+                match k.code {
+                    KeyCode::Esc => {
+                        app.finder = None;
+                        app.status = "Find canceled".to_string();
+                    }
+                    KeyCode::Up => finder.move_selection(-1),
+                    KeyCode::Down => finder.move_selection(1),
+                    KeyCode::PageUp => finder.move_selection(-10),
+                    KeyCode::PageDown => finder.move_selection(10),
+                    KeyCode::Backspace => {
+                        let mut query = finder.query.clone();
+                        query.pop();
+                        finder.set_query(query);
+                    }
+                    KeyCode::Char(c) if !k.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let query = format!("{}{}", finder.query, c);
+                        finder.set_query(query);
+                    }
+                    KeyCode::Enter => {
+                        if let Some(path) = finder.selected_path() {
+                            app.finder = None;
+                            app.fuzzy = None;
+                            reveal_path(app, &path);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        if app.transfer.is_some() {
+            if let Event::Key(k) = ev
+                && k.kind == KeyEventKind::Press
+            {
+                transfer_key(app, k);
+            }
+            continue;
+        }
+
+        if app.trash.is_some() {
+            if let Event::Key(k) = ev
+                && (k.kind == KeyEventKind::Press || k.kind == KeyEventKind::Repeat)
+            {
+                trash_key(app, k);
+            }
+            continue;
+        }
+
         if let Some(menu) = app.open_menu.as_mut() {
-            match event::read().expect("failed to read event") {
+            match ev {
                 // accept Press or Repeat so we don't skip alternating keys
                 Event::Key(k)
                     if k.kind == KeyEventKind::Press || k.kind == KeyEventKind::Repeat =>
@@ -84,53 +377,338 @@ fn main() {
                                 menu.selected = (menu.selected + 1) % menu.items.len();
                             }
                         }
-                        KeyCode::Enter => {
-                            // act on the selected opener (existing logic)
-                            if let Some(sel_idx) = app.selected {
-                                let flat = fs::tree::flatten(&app.entries);
-                                if let Some(it) = flat.get(sel_idx) {
-                                    let res = if menu.selected == 0 {
-                                        events::open_path(&it.path)
-                                    } else {
-                                        let spec = &menu.items[menu.selected];
-                                        events::open_with_spec(spec, &it.path)
-                                    };
-                                    match res {
-                                        Ok(_) => {
-                                            app.status = format!("Launched opener for {}", it.name)
-                                        }
-                                        Err(e) => app.status = format!("Open failed: {}", e),
-                                    }
-                                }
-                            }
-                            app.open_menu = None;
-                        }
+                        KeyCode::Enter => launch_opener(app),
                         _ => {}
                     }
                 }
+                // a click on an entry launches it, a click anywhere else closes the menu
+                Event::Mouse(m) => match m.kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        let clicked = app.layout.menu.and_then(|area| {
+                            ui::Layout::row_at(area, app.layout.menu_offset, m.column, m.row)
+                        });
+                        match clicked.filter(|&i| i < menu.items.len()) {
+                            Some(i) => {
+                                menu.selected = i;
+                                launch_opener(app);
+                            }
+                            None => {
+                                app.open_menu = None;
+                                app.status = "Open with canceled".to_string();
+                            }
+                        }
+                    }
+                    MouseEventKind::ScrollUp => menu.selected = menu.selected.saturating_sub(1),
+                    MouseEventKind::ScrollDown => {
+                        menu.selected = (menu.selected + 1).min(menu.items.len().saturating_sub(1))
+                    }
+                    _ => {}
+                },
                 // ignore other events while menu is active
                 _ => {}
             }
             continue;
         }
 
-        match event::read().expect("failed to read event") {
+        if app.prompt.is_some() {
+            if let Event::Key(k) = ev
+                && (k.kind == KeyEventKind::Press || k.kind == KeyEventKind::Repeat)
+            {
+                prompt_key(app, k);
+            }
+            continue;
+        }
+
+        if let Some(target) = app.save.as_ref().and_then(|s| s.confirm_overwrite.clone()) {
+            if let Event::Key(k) = ev
+                && k.kind == KeyEventKind::Press
+            {
+                match k.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => return Some(vec![target]),
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        if let Some(save) = app.save.as_mut() {
+                            save.confirm_overwrite = None;
+                        }
+                        app.status = "Save canceled".to_string();
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        match ev {
+            // While the fuzzy query line is being edited, typing goes to the query
+            Event::Key(k)
+                if k.kind == KeyEventKind::Press
+                    && app.fuzzy.as_ref().is_some_and(|f| f.editing)
+                    && matches!(
+                        k.code,
+                        KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter | KeyCode::Esc
+                    )
+                    && !k.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                match k.code {
+                    KeyCode::Char(c) => {
+                        if let Some(f) = app.fuzzy.as_mut() {
+                            f.query.push(c);
+                        }
+                        select_best_match(app);
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(f) = app.fuzzy.as_mut() {
+                            f.query.pop();
+                        }
+                        select_best_match(app);
+                    }
+                    KeyCode::Enter => {
+                        if app.fuzzy.as_ref().is_some_and(|f| f.query.is_empty()) {
+                            clear_fuzzy(app);
+                        } else if let Some(f) = app.fuzzy.as_mut() {
+                            f.editing = false;
+                            app.status = format!("Filtering by \"{}\". Esc clears.", f.query);
+                        }
+                    }
+                    KeyCode::Esc => clear_fuzzy(app),
+                    _ => {}
+                }
+            }
+            // `/` can't appear in a file name, so it opens the filter in every mode
+            Event::Key(k) if k.kind == KeyEventKind::Press && k.code == KeyCode::Char('/') => {
+                let query = app.fuzzy.take().map(|f| f.query).unwrap_or_default();
+                app.fuzzy = Some(fuzzy::FuzzyFilter {
+                    query,
+                    editing: true,
+                });
+                app.status = "Type to filter. Enter keeps the filter, Esc clears it.".to_string();
+            }
+            // With the preview focused, navigation keys browse its key tree
+            Event::Key(k) if k.kind == KeyEventKind::Press && app.focus_preview => {
+                browse_preview(app, k.code);
+            }
+            // In save mode typing goes to the file name input instead of commands
+            Event::Key(k)
+                if k.kind == KeyEventKind::Press
+                    && app.save.is_some()
+                    && matches!(
+                        k.code,
+                        KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Tab | KeyCode::Enter
+                    )
+                    && !k.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                match k.code {
+                    KeyCode::Char(c) => {
+                        if let Some(save) = app.save.as_mut() {
+                            save.name.push(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(save) = app.save.as_mut() {
+                            save.name.pop();
+                        }
+                    }
+                    KeyCode::Tab => {
+                        if let Some(it) = app.selected_item()
+                            && !it.is_dir
+                            && !it.placeholder
+                            && let Some(save) = app.save.as_mut()
+                        {
+                            save.name = it.name.clone();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        if let Some(paths) = confirm_save(app) {
+                            return Some(paths);
+                        }
+                    }
+                    _ => {}
+                }
+            }
             Event::Key(k) if k.kind == KeyEventKind::Press => match k.code {
-                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Esc if app.fuzzy.is_some() => clear_fuzzy(app),
+                KeyCode::Esc if app.du.running() => {
+                    app.du.cancel();
+                    app.status = "Folder sizing canceled".to_string();
+                }
+                KeyCode::Char('p') if k.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.finder = Some(finder::Finder::start(&app.root, &app.list));
+                    app.status =
+                        "Find file: type to search, Enter reveals, Esc cancels".to_string();
+                }
+                KeyCode::Char('q') | KeyCode::Esc => return None,
                 KeyCode::Char('o') => {
-                    if let Some(i) = app.selected {
-                        let flat = fs::tree::flatten(&app.entries);
-                        if let Some(it) = flat.get(i) {
-                            let items = build_openers_for(&it.path);
-                            if !items.is_empty() {
-                                app.open_menu = Some(OpenMenu { items, selected: 0 });
-                                app.status = format!("Open with: {}", it.name);
+                    if let Some(it) = app.selected_item().filter(|it| !it.placeholder) {
+                        let items = build_openers_for(&it.path);
+                        if !items.is_empty() {
+                            app.open_menu = Some(OpenMenu { items, selected: 0 });
+                            app.status = format!("Open with: {}", it.name);
+                        }
+                    }
+                }
+                KeyCode::Char('p') => {
+                    app.show_preview = !app.show_preview;
+                }
+                // the folder being browsed has no row of its own, and may have no rows at all
+                KeyCode::Char('P') if app.mode == picker::Mode::Directory => {
+                    return Some(vec![app.root.clone()]);
+                }
+                KeyCode::Char('n') => start_op(app, ops::Action::NewFile),
+                KeyCode::Char('N') => start_op(app, ops::Action::NewDir),
+                KeyCode::Char('r') if k.modifiers.contains(KeyModifiers::CONTROL) => {
+                    undo_redo(app, false)
+                }
+                KeyCode::Char('u') => undo_redo(app, true),
+                KeyCode::Char('r') => start_op(app, ops::Action::Rename),
+                KeyCode::Char('c') => start_op(app, ops::Action::Copy),
+                KeyCode::Char('m') => start_op(app, ops::Action::Move),
+                KeyCode::Delete if k.modifiers.contains(KeyModifiers::SHIFT) => {
+                    start_op(app, ops::Action::Delete)
+                }
+                KeyCode::Delete => start_op(app, ops::Action::Trash),
+                KeyCode::Char('T') => {
+                    let browser = trash::Browser::open();
+                    app.status = if browser.items.is_empty() {
+                        "The trash is empty. Esc closes".to_string()
+                    } else {
+                        "Trash: Enter restores, Delete removes for good, E empties, Esc closes"
+                            .to_string()
+                    };
+                    app.trash = Some(browser);
+                }
+                KeyCode::Char('x') => {
+                    if let Some(it) = app.selected_item()
+                        && !it.is_dir
+                        && !it.placeholder
+                    {
+                        match hexview::HexView::open(&it.path) {
+                            Ok(hex) => {
+                                app.status = format!(
+                                    "{}: g goto offset, / search, n/N next/previous, q closes",
+                                    it.name
+                                );
+                                app.hex = Some(hex);
                             }
+                            Err(e) => app.status = format!("Can't open {}: {}", it.name, e),
+                        }
+                    }
+                }
+                KeyCode::Tab => {
+                    let width = terminal.size().map(|s| s.width).unwrap_or(0);
+                    if app.preview_shown(width)
+                        && app
+                            .preview
+                            .as_ref()
+                            .is_some_and(|c| matches!(c.preview, preview::Preview::Data { .. }))
+                    {
+                        app.focus_preview = true;
+                        app.status = "Browsing preview: arrows move and fold, Tab or Esc returns"
+                            .to_string();
+                    }
+                }
+                KeyCode::Char(c @ '1'..='4') => {
+                    let column = [
+                        columns::Column::Size,
+                        columns::Column::Mtime,
+                        columns::Column::Perms,
+                        columns::Column::Owner,
+                    ][c as usize - '1' as usize];
+                    let shown = app.columns.toggle(column);
+                    app.status = format!(
+                        "{} {} column",
+                        if shown { "Showing" } else { "Hiding" },
+                        column.label()
+                    );
+                }
+                KeyCode::Char('d') => {
+                    if let Some(it) = app.selected_item()
+                        && it.is_dir
+                    {
+                        // always counted afresh, the folder may have changed
+                        app.status = if app.du.start(vec![it.path.clone()], app.tasks.sender()) > 0
+                        {
+                            format!("Sizing {}… Esc cancels", it.name)
+                        } else {
+                            format!("{} is already being sized", it.name)
+                        };
+                    }
+                }
+                KeyCode::Char('D') => {
+                    let dirs: Vec<PathBuf> = app
+                        .view
+                        .nodes(&app.entries)
+                        .filter(|n| n.is_dir && !app.du.sized(&n.path))
+                        .map(|n| n.path.clone())
+                        .collect();
+                    app.status = match app.du.start(dirs, app.tasks.sender()) {
+                        0 => "All listed folders are already sized".to_string(),
+                        n => format!("Sizing {} folders… Esc cancels", n),
+                    };
+                }
+                KeyCode::Char('t') => {
+                    app.columns.absolute_time = !app.columns.absolute_time;
+                    app.status = if app.columns.absolute_time {
+                        "Showing modification dates".to_string()
+                    } else {
+                        "Showing modification ages".to_string()
+                    };
+                }
+                KeyCode::Char('.') => {
+                    app.list.show_hidden = !app.list.show_hidden;
+                    reload_tree(app);
+                    app.status = if app.list.show_hidden {
+                        "Showing hidden files".to_string()
+                    } else {
+                        "Hiding hidden files".to_string()
+                    };
+                }
+                KeyCode::Char('i') => {
+                    app.list.respect_ignore = !app.list.respect_ignore;
+                    reload_tree(app);
+                    app.status = if app.list.respect_ignore {
+                        "Hiding ignored files".to_string()
+                    } else {
+                        "Showing ignored files".to_string()
+                    };
+                }
+                KeyCode::Char('s') => {
+                    app.list.sort.key = app.list.sort.key.next();
+                    resort(app);
+                }
+                KeyCode::Char('S') => {
+                    app.list.sort.reverse = !app.list.sort.reverse;
+                    resort(app);
+                }
+                KeyCode::Char('f') => {
+                    app.list.sort.dirs_first = !app.list.sort.dirs_first;
+                    resort_tree(app);
+                    app.status = if app.list.sort.dirs_first {
+                        "Listing folders first".to_string()
+                    } else {
+                        "Listing folders among files".to_string()
+                    };
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(i) = app.selected
+                        && let Some(it) = app.item(i).filter(|it| !it.placeholder)
+                    {
+                        if !app.marked.contains(&it.path) && !app.mode.accepts(it.is_dir) {
+                            app.status = format!("Only {} can be marked", app.mode.describe());
+                            continue;
+                        }
+                        if app.marked.remove(&it.path) {
+                            app.status = format!("Unmarked {}", it.name);
+                        } else {
+                            app.marked.insert(it.path.clone());
+                            app.status = format!("Marked {}", it.name);
+                        }
+                        app.status += &format!(" ({} marked)", app.marked.len());
+                        if i + 1 < app.view.len() {
+                            app.selected = Some(i + 1);
                         }
                     }
                 }
                 KeyCode::Down => {
-                    let flat_len = fs::tree::flatten(&app.entries).len();
+                    let flat_len = app.view.len();
                     if flat_len == 0 {
                         continue;
                     }
@@ -143,180 +721,170 @@ fn main() {
                     }
                 }
                 KeyCode::Up => {
-                    let flat_len = fs::tree::flatten(&app.entries).len();
+                    let flat_len = app.view.len();
                     if flat_len == 0 {
                         continue;
                     }
-                    if let Some(i) = app.selected {
-                        if i > 0 {
-                            app.selected = Some(i - 1);
-                        }
+                    if let Some(i) = app.selected
+                        && i > 0
+                    {
+                        app.selected = Some(i - 1);
                     }
                 }
                 KeyCode::Right => {
-                    if let Some(i) = app.selected {
-                        let flat = fs::tree::flatten(&app.entries);
-                        if let Some(it) = flat.get(i) {
-                            if it.is_dir {
-                                let idx = it.idx_path.clone();
-                                if let Some(node) = with_node_mut(&mut app.entries, &idx) {
-                                    if !node.is_expanded {
-                                        node.expand(); // load children lazily (implemented in your tree.rs)
-                                        app.status = format!("Expanded {}", node.name);
-                                    }
-                                }
-                            }
-                        }
+                    if let Some(it) = app.selected_item()
+                        && it.is_dir
+                        && let Some(node) = with_node_mut(&mut app.entries, &it.idx_path)
+                        && !node.is_expanded
+                    {
+                        loader::expand(node, &app.list, app.tasks.sender()); // children stream in
+                        app.status = format!("Expanded {}", node.name);
+                        app.refresh_rows(&it.idx_path);
                     }
-                    clamp_selected(&mut app);
+                    clamp_selected(app);
                 }
                 KeyCode::Left => {
-                    if let Some(i) = app.selected {
-                        let flat = fs::tree::flatten(&app.entries);
-                        if let Some(it) = flat.get(i) {
-                            if it.is_dir {
-                                let idx = it.idx_path.clone();
-                                if let Some(node) = with_node_mut(&mut app.entries, &idx) {
-                                    if node.is_expanded {
-                                        node.collapse();
-                                        app.status = format!("Collapsed {}", node.name);
-                                    }
-                                }
-                            }
-                        }
+                    if let Some(it) = app.selected_item()
+                        && it.is_dir
+                        && let Some(node) = with_node_mut(&mut app.entries, &it.idx_path)
+                        && node.is_expanded
+                    {
+                        node.collapse();
+                        app.status = format!("Collapsed {}", node.name);
+                        app.refresh_rows(&it.idx_path);
                     }
-                    clamp_selected(&mut app);
+                    clamp_selected(app);
                 }
                 KeyCode::Enter => {
-                    if let Some(i) = app.selected {
-                        let flat = fs::tree::flatten(&app.entries);
-                        if let Some(it) = flat.get(i) {
-                            if it.is_dir {
-                                let idx = it.idx_path.clone();
-                                if let Some(node) = with_node_mut(&mut app.entries, &idx) {
-                                    if node.is_expanded {
-                                        node.collapse();
-                                        app.status = format!("Collapsed folder: {}", node.name);
-                                    } else {
-                                        node.expand();
-                                        app.status = format!("Expanded folder: {}", node.name);
-                                    }
+                    if let Some(it) = app.selected_item().filter(|it| !it.placeholder) {
+                        if it.is_dir && app.mode == picker::Mode::Directory {
+                            return Some(picked_paths(app, &it.path));
+                        } else if it.is_dir {
+                            if let Some(node) = with_node_mut(&mut app.entries, &it.idx_path) {
+                                if node.is_expanded {
+                                    node.collapse();
+                                    app.status = format!("Collapsed folder: {}", node.name);
+                                } else {
+                                    loader::expand(node, &app.list, app.tasks.sender());
+                                    app.status = format!("Expanded folder: {}", node.name);
                                 }
-                            } else {
-                                match events::open_path(&it.path) {
-                                    Ok(_) => app.status = format!("Opening {}", it.path.display()),
-                                    Err(e) => {
-                                        app.status =
-                                            format!("Failed to open {}: {}", it.path.display(), e)
-                                    }
+                                app.refresh_rows(&it.idx_path);
+                            }
+                        } else if !app.mode.accepts(false) {
+                            app.status = format!("Only {} can be picked", app.mode.describe());
+                        } else if app.pick {
+                            return Some(picked_paths(app, &it.path));
+                        } else {
+                            match events::open_path(&it.path) {
+                                Ok(_) => app.status = format!("Opening {}", it.path.display()),
+                                Err(e) => {
+                                    app.status =
+                                        format!("Failed to open {}: {}", it.path.display(), e)
                                 }
                             }
                         }
                     }
-                    clamp_selected(&mut app);
+                    clamp_selected(app);
                 }
                 _ => {}
             },
             Event::Mouse(m) => {
                 match m.kind {
                     MouseEventKind::Down(MouseButton::Left) => {
-                        // Map mouse row to list index (account for borders + status bar)
-                        let (_w, h) = crossterm::terminal::size().unwrap_or((0, 0));
-                        if h >= 3 {
-                            let body_h = h.saturating_sub(1);
-                            let inner_start_y = 1u16;
-                            let inner_rows = body_h.saturating_sub(2);
-                            let y = m.row;
-                            if y >= inner_start_y && y < inner_start_y + inner_rows {
-                                let clicked_idx = (y - inner_start_y) as usize;
-                                let flat = fs::tree::flatten(&app.entries);
-                                if clicked_idx < flat.len() {
-                                    // Select on single click
-                                    app.selected = Some(clicked_idx);
-                                    app.status = format!("Selected {}", flat[clicked_idx].name);
-
-                                    // Detect double-click within 350ms on same row
-                                    let now = Instant::now();
-                                    let dbl_thresh = Duration::from_millis(350);
-                                    if let Some((last_idx, t)) = app.last_click {
-                                        if last_idx == clicked_idx
-                                            && now.duration_since(t) <= dbl_thresh
-                                        {
-                                            // Double-click: act on the item
-                                            let it = &flat[clicked_idx];
-                                            if it.is_dir {
-                                                let idx = it.idx_path.clone();
-                                                if let Some(node) =
-                                                    with_node_mut(&mut app.entries, &idx)
-                                                {
-                                                    if node.is_expanded {
-                                                        node.collapse();
-                                                        app.status =
-                                                            format!("Collapsed {}", node.name);
-                                                    } else {
-                                                        node.expand();
-                                                        app.status =
-                                                            format!("Expanded {}", node.name);
-                                                    }
-                                                }
-                                            } else {
-                                                match events::open_path(&it.path) {
-                                                    Ok(_) => {
-                                                        app.status =
-                                                            format!("Opening {}", it.path.display())
-                                                    }
-                                                    Err(e) => {
-                                                        app.status = format!(
-                                                            "Failed to open {}: {}",
-                                                            it.path.display(),
-                                                            e
-                                                        )
-                                                    }
-                                                }
-                                            }
-                                            clamp_selected(&mut app);
-                                            app.last_click = None; // reset after double-click
-                                            continue;
+                        // Map the click to a row through the rect the tree was last drawn in
+                        if let Some(clicked_idx) =
+                            ui::Layout::row_at(app.layout.tree, app.offset, m.column, m.row)
+                            && let Some(it) = app.item(clicked_idx)
+                        {
+                            // Select on single click
+                            app.selected = Some(clicked_idx);
+                            app.status = format!("Selected {}", it.name);
+
+                            // Detect double-click within 350ms on same row
+                            let now = Instant::now();
+                            let dbl_thresh = Duration::from_millis(350);
+                            if let Some((last_idx, t)) = app.last_click
+                                && last_idx == clicked_idx
+                                && now.duration_since(t) <= dbl_thresh
+                            {
+                                // Double-click: act on the item
+                                if it.placeholder {
+                                    // nothing to act on yet
+                                } else if it.is_dir {
+                                    if let Some(node) =
+                                        with_node_mut(&mut app.entries, &it.idx_path)
+                                    {
+                                        if node.is_expanded {
+                                            node.collapse();
+                                            app.status = format!("Collapsed {}", node.name);
+                                        } else {
+                                            loader::expand(node, &app.list, app.tasks.sender());
+                                            app.status = format!("Expanded {}", node.name);
+                                        }
+                                        app.refresh_rows(&it.idx_path);
+                                    }
+                                } else if app.save.is_some() {
+                                    if let Some(save) = app.save.as_mut() {
+                                        save.name = it.name.clone();
+                                    }
+                                    if let Some(paths) = confirm_save(app) {
+                                        return Some(paths);
+                                    }
+                                } else if !app.mode.accepts(false) {
+                                    app.status =
+                                        format!("Only {} can be picked", app.mode.describe());
+                                } else if app.pick {
+                                    return Some(picked_paths(app, &it.path));
+                                } else {
+                                    match events::open_path(&it.path) {
+                                        Ok(_) => {
+                                            app.status = format!("Opening {}", it.path.display())
+                                        }
+                                        Err(e) => {
+                                            app.status = format!(
+                                                "Failed to open {}: {}",
+                                                it.path.display(),
+                                                e
+                                            )
                                         }
                                     }
-                                    // Not a double-click; remember this click
-                                    app.last_click = Some((clicked_idx, now));
                                 }
+                                clamp_selected(app);
+                                app.last_click = None; // reset after double-click
+                                continue;
                             }
+                            // Not a double-click; remember this click
+                            app.last_click = Some((clicked_idx, now));
                         }
                     }
                     MouseEventKind::ScrollUp => {
-                        let flat_len = fs::tree::flatten(&app.entries).len();
+                        let flat_len = app.view.len();
                         if flat_len == 0 {
                             continue;
                         }
-                        if let Some(i) = app.selected {
-                            if i > 0 {
-                                app.selected = Some(i - 1);
-                                let flat = fs::tree::flatten(&app.entries);
-                                app.status = format!("Selected {}", flat[i - 1].name);
-                            }
-                        } else {
-                            app.selected = Some(0);
-                            let flat = fs::tree::flatten(&app.entries);
-                            app.status = format!("Selected {}", flat[0].name);
+                        let next = match app.selected {
+                            Some(i) if i > 0 => Some(i - 1),
+                            Some(_) => None,
+                            None => Some(0),
+                        };
+                        if let Some(it) = next.and_then(|i| app.item(i)) {
+                            app.selected = next;
+                            app.status = format!("Selected {}", it.name);
                         }
                     }
                     MouseEventKind::ScrollDown => {
-                        let flat_len = fs::tree::flatten(&app.entries).len();
+                        let flat_len = app.view.len();
                         if flat_len == 0 {
                             continue;
                         }
-                        if let Some(i) = app.selected {
-                            if i + 1 < flat_len {
-                                app.selected = Some(i + 1);
-                                let flat = fs::tree::flatten(&app.entries);
-                                app.status = format!("Selected {}", flat[i + 1].name);
-                            }
-                        } else {
-                            app.selected = Some(0);
-                            let flat = fs::tree::flatten(&app.entries);
-                            app.status = format!("Selected {}", flat[0].name);
+                        let next = match app.selected {
+                            Some(i) if i + 1 < flat_len => Some(i + 1),
+                            Some(_) => None,
+                            None => Some(0),
+                        };
+                        if let Some(it) = next.and_then(|i| app.item(i)) {
+                            app.selected = next;
+                            app.status = format!("Selected {}", it.name);
                         }
                     }
                     _ => {
@@ -328,10 +896,731 @@ fn main() {
             _ => {}
         }
     }
+}
+
+// Run the opener chosen in the open-with menu on the selected entry and close
+// the menu.
+fn launch_opener(app: &mut App) {
+    let Some(menu) = app.open_menu.take() else {
+        return;
+    };
+    let spec = (menu.selected > 0).then(|| menu.items[menu.selected].clone());
+    if let Some(it) = app.selected_item() {
+        let res = match &spec {
+            None => events::open_path(&it.path),
+            Some(spec) => events::open_with_spec(spec, &it.path),
+        };
+        match res {
+            Ok(_) => app.status = format!("Launched opener for {}", it.name),
+            Err(e) => app.status = format!("Open failed: {}", e),
+        }
+    }
+}
+
+// Open the prompt for `action`. Renames apply to the selected entry; copy, move
+// and delete to the marked entries, or the selected one when nothing is marked.
+// New entries go into the selected folder, or next to the selected file.
+fn start_op(app: &mut App, action: ops::Action) {
+    let current = app.selected_item().filter(|it| !it.placeholder);
+    let here = picker::SaveDialog::target_dir(
+        &app.root,
+        current.as_ref().map(|it| (it.path.as_path(), it.is_dir)),
+    );
+    let (targets, dir) = match (action, current) {
+        (ops::Action::NewFile | ops::Action::NewDir, _) => (Vec::new(), here),
+        (ops::Action::Rename, None) => return,
+        (ops::Action::Rename, Some(it)) => (vec![it.path], here),
+        (_, _) if !app.marked.is_empty() => (app.marked.iter().cloned().collect(), here),
+        (_, None) => return,
+        (_, Some(it)) => {
+            let parent = it.path.parent().unwrap_or(&app.root).to_path_buf();
+            (vec![it.path], parent)
+        }
+    };
+    app.status = match action {
+        ops::Action::NewFile | ops::Action::NewDir => {
+            "Type a name, Enter creates it, Esc cancels".to_string()
+        }
+        ops::Action::Rename => "Type the new name, Enter to confirm, Esc cancels".to_string(),
+        ops::Action::Copy | ops::Action::Move => format!(
+            "Edit the destination folder, Enter to {}, Esc cancels",
+            action.label().to_lowercase()
+        ),
+        ops::Action::Trash => "Press y to move to the trash, n or Esc to keep".to_string(),
+        ops::Action::Delete => "Press y to delete for good, n or Esc to keep".to_string(),
+    };
+    app.prompt = Some(ops::Prompt::new(action, targets, dir));
+}
+
+// Keys while an operation prompt is open: typing edits the name or destination,
+// Enter goes ahead (renames ask once more), y/n answer the confirmation.
+fn prompt_key(app: &mut App, k: event::KeyEvent) {
+    let Some(prompt) = app.prompt.as_mut() else {
+        return;
+    };
+    if prompt.confirming {
+        match k.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => run_op(app),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                app.status = format!("{} canceled", prompt.action.label());
+                app.prompt = None;
+            }
+            _ => {}
+        }
+        return;
+    }
+    match k.code {
+        KeyCode::Char(c) if !k.modifiers.contains(KeyModifiers::CONTROL) => prompt.input.push(c),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Esc => {
+            app.status = format!("{} canceled", prompt.action.label());
+            app.prompt = None;
+        }
+        KeyCode::Enter if prompt.action == ops::Action::Rename => {
+            prompt.confirming = true;
+            app.status = "Press y to rename, n or Esc to cancel".to_string();
+        }
+        KeyCode::Enter => run_op(app),
+        _ => {}
+    }
+}
+
+// Carry out the prompted operation, refresh the folders it touched and select what
+// it created.
+fn run_op(app: &mut App) {
+    let Some(prompt) = app.prompt.take() else {
+        return;
+    };
+    if matches!(prompt.action, ops::Action::Copy | ops::Action::Move) {
+        start_transfer(app, prompt);
+        return;
+    }
+    let mut steps = Vec::new();
+    let result = prompt.run(&mut steps);
+    app.journal.record(prompt.done(), steps);
+    for target in &prompt.targets {
+        app.marked.remove(target);
+    }
+    refresh_dirs(app, &prompt.affected());
+    match result {
+        Ok(select) => {
+            if let Some(path) = select {
+                select_when_loaded(app, path);
+            }
+            app.status = prompt.done();
+        }
+        Err(e) => app.status = format!("{} failed: {}", prompt.action.label(), e),
+    }
+}
+
+// Hand a copy or move over to a worker; progress and conflicts come back as tasks.
+fn start_transfer(app: &mut App, prompt: ops::Prompt) {
+    let pairs = match prompt.transfers() {
+        Ok(pairs) => pairs,
+        Err(e) => {
+            app.status = format!("{} failed: {}", prompt.action.label(), e);
+            return;
+        }
+    };
+    for target in &prompt.targets {
+        app.marked.remove(target);
+    }
+    let moving = prompt.action == ops::Action::Move;
+    app.status = format!(
+        "{} {}, Esc cancels",
+        if moving { "Moving" } else { "Copying" },
+        match pairs.as_slice() {
+            [(src, _)] => src.display().to_string(),
+            many => format!("{} entries", many.len()),
+        }
+    );
+    app.transfer = Some(transfer::Transfer::start(
+        moving,
+        pairs,
+        prompt.done(),
+        prompt.affected(),
+        app.tasks.sender(),
+    ));
+}
+
+// Record what a finished transfer did and show where things ended up.
+fn finish_transfer(app: &mut App, done: transfer::Done) {
+    let Some(transfer) = app.transfer.take() else {
+        return;
+    };
+    let canceled = transfer.canceled();
+    if let Some(undo) = transfer.undo {
+        finish_replay(app, transfer.label.clone(), undo, canceled, done);
+        return;
+    }
+    app.journal.record(transfer.label.clone(), done.steps);
+    refresh_dirs(app, &transfer.dirs);
+    if let Some(path) = done.last {
+        select_when_loaded(app, path);
+    }
+    let action = if transfer.moving { "Move" } else { "Copy" };
+    app.status = match done.result {
+        _ if canceled => format!("{} canceled", action),
+        Err(e) => format!("{} failed: {}", action, e),
+        Ok(()) if done.skipped > 0 => format!("{}, skipped {}", transfer.label, done.skipped),
+        Ok(()) => transfer.label.clone(),
+    };
+}
+
+// Keys while a transfer runs: Esc cancels it, and when a destination exists
+// o/s/r pick overwrite, skip or rename, c compares both and a toggles whether the
+// choice goes for all later conflicts.
+fn transfer_key(app: &mut App, k: event::KeyEvent) {
+    let Some(transfer) = app.transfer.as_mut() else {
+        return;
+    };
+    match k.code {
+        KeyCode::Esc => {
+            transfer.cancel();
+            app.status = "Canceling…".to_string();
+        }
+        _ if transfer.conflict.is_none() => {}
+        KeyCode::Char(c @ ('o' | 's' | 'r')) => {
+            transfer.choose(match c {
+                'o' => transfer::Choice::Overwrite,
+                's' => transfer::Choice::Skip,
+                _ => transfer::Choice::Rename,
+            });
+            app.status = format!("{}, Esc cancels", transfer.doing());
+        }
+        KeyCode::Char('c') => transfer.compare(),
+        KeyCode::Char('a') => transfer.apply_to_all = !transfer.apply_to_all,
+        _ => {}
+    }
+}
+
+// Undo the last file operation, or redo the last undone one. It runs on a transfer
+// worker, so moving things back shows progress and asks about conflicts.
+fn undo_redo(app: &mut App, undo: bool) {
+    let Some((label, steps)) = app.journal.take(undo) else {
+        app.status = format!("Nothing to {}", if undo { "undo" } else { "redo" });
+        return;
+    };
+    app.status = format!(
+        "{} {}, Esc cancels",
+        if undo { "Undoing" } else { "Redoing" },
+        label
+    );
+    app.transfer = Some(transfer::Transfer::replay(
+        label,
+        steps,
+        undo,
+        app.tasks.sender(),
+    ));
+}
+
+// Put a replayed operation back in the journal and show the result.
+fn finish_replay(app: &mut App, label: String, undo: bool, canceled: bool, done: transfer::Done) {
+    let skipped = done.skipped;
+    let outcome = app.journal.settle(undo, label, done);
+    refresh_dirs(app, &outcome.dirs);
+    if let Some(path) = outcome.select {
+        select_when_loaded(app, path);
+    }
+    let (action, did) = if undo {
+        ("Undo", "Undid")
+    } else {
+        ("Redo", "Redid")
+    };
+    app.status = match outcome.result {
+        _ if canceled => format!("{} canceled", action),
+        Err(e) => format!("{} failed: {}", action, e),
+        // what was skipped stays in the journal to try again
+        Ok(()) if skipped > 0 => format!("{}: {}, skipped {}", did, outcome.label, skipped),
+        Ok(()) => format!("{}: {}", did, outcome.label),
+    };
+}
+
+// Keys in the trash browser: arrows move, Enter or `r` restores the selected item,
+// Delete removes it for good after a y/n, `E` empties the trash after a y/n, `q`
+// or Esc close the browser.
+fn trash_key(app: &mut App, k: event::KeyEvent) {
+    let Some(browser) = app.trash.as_mut() else {
+        return;
+    };
+    if browser.confirm_empty {
+        browser.confirm_empty = false;
+        if !matches!(k.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            app.status = "Kept the trash".to_string();
+            return;
+        }
+        let result = trash::empty(&browser.items);
+        // whatever couldn't be deleted stays listed
+        browser.items = trash::list();
+        browser.move_selection(0);
+        app.status = match result {
+            Ok(n) => format!("Emptied the trash ({} items)", n),
+            Err(e) => format!("Emptying the trash failed: {}", e),
+        };
+        return;
+    }
+    if browser.confirm_purge {
+        browser.confirm_purge = false;
+        let Some(item) = browser.current().cloned() else {
+            return;
+        };
+        if !matches!(k.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            app.status = "Kept in the trash".to_string();
+            return;
+        }
+        match trash::purge(&item) {
+            Ok(()) => {
+                browser.remove_current();
+                app.status = format!("Deleted {} for good", item.original.display());
+            }
+            Err(e) => app.status = format!("Delete failed: {}", e),
+        }
+        return;
+    }
+    match k.code {
+        KeyCode::Char('q') | KeyCode::Esc => {
+            app.trash = None;
+            app.status = "Closed trash".to_string();
+        }
+        KeyCode::Up => browser.move_selection(-1),
+        KeyCode::Down => browser.move_selection(1),
+        KeyCode::PageUp => browser.move_selection(-10),
+        KeyCode::PageDown => browser.move_selection(10),
+        KeyCode::Delete if browser.current().is_some() => {
+            browser.confirm_purge = true;
+            app.status = "Delete it for good? (y/n)".to_string();
+        }
+        KeyCode::Char('E') if !browser.items.is_empty() => {
+            browser.confirm_empty = true;
+            app.status = format!(
+                "Delete all {} items in the trash for good? (y/n)",
+                browser.items.len()
+            );
+        }
+        KeyCode::Enter | KeyCode::Char('r') => {
+            let Some(item) = browser.current().cloned() else {
+                return;
+            };
+            match trash::restore(&item) {
+                Ok(()) => {
+                    browser.remove_current();
+                    if let Some(parent) = item.original.parent() {
+                        refresh_dirs(app, &[parent.to_path_buf()]);
+                    }
+                    app.status = format!("Restored {}", item.original.display());
+                }
+                Err(e) => app.status = format!("Restore failed: {}", e),
+            }
+        }
+        _ => {}
+    }
+}
+
+// Marked entries win over the entry under the cursor when picking.
+fn picked_paths(app: &App, current: &Path) -> Vec<PathBuf> {
+    if app.marked.is_empty() {
+        vec![current.to_path_buf()]
+    } else {
+        app.marked.iter().cloned().collect()
+    }
+}
+
+// Move the selection to the highest ranked entry whose own name matches.
+fn select_best_match(app: &mut App) {
+    app.rebuild_view();
+    app.selected = app.view.first_match().or(Some(0));
+    clamp_selected(app);
+}
+
+// Drop the fuzzy filter, keeping the cursor on the same entry.
+fn clear_fuzzy(app: &mut App) {
+    let current = app.selected_item().map(|it| it.path);
+    app.fuzzy = None;
+    app.rebuild_view();
+    if let Some(path) = current {
+        app.selected = app.view.position(&app.entries, &path).or(Some(0));
+    }
+    clamp_selected(app);
+    app.status = "Filter cleared".to_string();
+}
+
+// Apply whatever background workers reported since the last tick.
+fn handle_tasks(app: &mut App) {
+    for result in app.tasks.drain() {
+        match result {
+            tasks::TaskResult::DirSize(update) => {
+                let was_running = app.du.running();
+                if app.du.receive(update) && was_running && !app.du.running() {
+                    app.status = "Folder sizes computed".to_string();
+                }
+            }
+            tasks::TaskResult::DirBatch(batch) => {
+                let current = app.selected_item().map(|it| it.path);
+                let dir = batch.dir.clone();
+                if !loader::receive(&mut app.entries, batch, &app.list) {
+                    continue;
+                }
+                app.du.reapply();
+                if let Some(idx) = fs::tree::index_of(&app.entries, &dir) {
+                    app.view.refresh(&app.entries, &idx);
+                }
+                if let Some(path) = current {
+                    // rows may have been inserted above the cursor
+                    app.selected = app.view.position(&app.entries, &path);
+                    clamp_selected(app);
+                }
+            }
+            tasks::TaskResult::Changed(dir) => app.watcher.changed(dir),
+            tasks::TaskResult::Listing(listing) => {
+                let current = app.selected_item().map(|it| it.path);
+                let tx = app.tasks.sender();
+                let Some(idx) =
+                    app.rereads
+                        .receive(&app.root, &mut app.entries, listing, &app.list, tx)
+                else {
+                    continue;
+                };
+                if idx.is_empty() {
+                    app.rebuild_view();
+                } else {
+                    app.refresh_rows(&idx);
+                    app.du.reapply();
+                }
+                if let Some(path) = current {
+                    app.selected = app.view.position(&app.entries, &path).or(app.selected);
+                }
+                clamp_selected(app);
+                if !app.rereads.busy()
+                    && let Some(target) = app.select_after.take()
+                {
+                    select_path(app, &target);
+                }
+            }
+            tasks::TaskResult::Transfer(update) => {
+                let Some(transfer) = app.transfer.as_mut() else {
+                    continue;
+                };
+                if let Some(done) = transfer.receive(update) {
+                    finish_transfer(app, done);
+                } else if let Some(conflict) = &transfer.conflict {
+                    app.status = format!("{} already exists", conflict.dst.display());
+                }
+            }
+            tasks::TaskResult::HexFound(found) => {
+                if let Some(hex) = app.hex.as_mut()
+                    && let Some(result) = hex.receive(found)
+                {
+                    app.status = match result {
+                        Ok(Some(at)) => format!("Match at offset 0x{:x}", at),
+                        Ok(None) => "Pattern not found".to_string(),
+                        Err(e) => format!("Search failed: {}", e),
+                    };
+                }
+            }
+            tasks::TaskResult::Preview(path, preview) => {
+                if let Some(cache) = app.preview.as_mut()
+                    && cache.path == path
+                {
+                    cache.preview = preview;
+                }
+            }
+        }
+    }
+}
+
+// Re-read the watched directories whose entries changed.
+fn apply_changes(app: &mut App) {
+    let dirs = app.watcher.due();
+    if dirs.is_empty() {
+        return;
+    }
+    refresh_dirs(app, &dirs);
+    // the previewed entry may have been rewritten; the old preview stays up meanwhile
+    if let Some(it) = app.selected_item()
+        && app.preview.as_ref().is_some_and(|p| p.path == it.path)
+        && dirs
+            .iter()
+            .any(|d| it.path == *d || it.path.parent() == Some(d))
+    {
+        load_preview(app, it.path, it.is_dir);
+    }
+}
+
+// Read the listings of those `dirs` that are loaded again in the background. They
+// are merged as they come back, keeping the cursor on the same entry (or in the
+// same place if that entry went away).
+fn refresh_dirs(app: &mut App, dirs: &[PathBuf]) {
+    for dir in dirs {
+        app.du.invalidate(dir);
+        app.list.ignores.forget(dir); // in case its ignore files changed
+        // folders still being read pick the change up from the running read
+        let loaded = *dir == app.root
+            || fs::tree::find_mut(&mut app.entries, dir)
+                .is_some_and(|node| node.is_expanded && node.loading.is_none());
+        if loaded {
+            app.rereads
+                .start(dir.clone(), &app.list, app.tasks.sender());
+        }
+    }
+}
+
+// Select `target` once the folders being read again are back, since an entry an
+// operation just created isn't in the tree before that.
+fn select_when_loaded(app: &mut App, target: PathBuf) {
+    if app.rereads.busy() {
+        app.select_after = Some(target);
+    } else {
+        select_path(app, &target);
+    }
+}
+
+// Put the idle status back once a message has been up for STATUS_TTL.
+fn expire_status(app: &mut App) {
+    if app.status != app.status_since.0 {
+        app.status_since = (app.status.clone(), Instant::now());
+    } else if app.status != app.idle_status && app.status_since.1.elapsed() >= STATUS_TTL {
+        app.status = app.idle_status.clone();
+    }
+}
+
+// Start loading the preview for the selected entry if the selection moved. It
+// shows as loading until the worker reports back.
+fn refresh_preview(app: &mut App) {
+    if !app.show_preview {
+        return;
+    }
+    let Some(it) = app.selected_item().filter(|it| !it.placeholder) else {
+        app.preview = None;
+        return;
+    };
+    if app.preview.as_ref().is_some_and(|p| p.path == it.path) {
+        return;
+    }
+    app.focus_preview = false;
+    app.preview = Some(preview::PreviewCache {
+        path: it.path.clone(),
+        preview: preview::Preview::Loading,
+    });
+    load_preview(app, it.path, it.is_dir);
+}
+
+// Read the preview of `path` on a worker; it replaces the cached one when done.
+fn load_preview(app: &App, path: PathBuf, is_dir: bool) {
+    let (opts, tx) = (app.list.clone(), app.tasks.sender());
+    thread::spawn(move || {
+        let preview = preview::load(&path, is_dir, &opts);
+        let _ = tx.send(tasks::TaskResult::Preview(path, preview));
+    });
+}
+
+// Keys in the hex viewer: arrows and paging move the cursor, `g` and `/` open the
+// goto and search prompts, `n`/`N` repeat the search, Esc stops a running search,
+// `q` or Esc close the viewer.
+fn hex_key(app: &mut App, k: event::KeyEvent) {
+    let Some(hex) = app.hex.as_mut() else {
+        return;
+    };
+    if let Some(input) = hex.input.as_mut() {
+        match k.code {
+            KeyCode::Char(c) if !k.modifiers.contains(KeyModifiers::CONTROL) => input.text.push(c),
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Esc => hex.input = None,
+            KeyCode::Enter => {
+                let Some(input) = hex.input.take() else {
+                    return;
+                };
+                match input.prompt {
+                    hexview::Prompt::Goto => match hexview::parse_offset(&input.text, hex.cursor) {
+                        Some(offset) => {
+                            hex.goto(offset);
+                            app.status = format!("At offset 0x{:x}", hex.cursor);
+                        }
+                        None => app.status = format!("Not an offset: {}", input.text),
+                    },
+                    hexview::Prompt::Search => match hexview::parse_pattern(&input.text) {
+                        Some(pattern) => {
+                            hex.pattern = Some(pattern);
+                            app.status = search_status(hex, false, app.tasks.sender());
+                        }
+                        None => app.status = "Empty search pattern".to_string(),
+                    },
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    let row = hexview::ROW as i64;
+    match k.code {
+        KeyCode::Esc if hex.cancel_search() => app.status = "Search canceled".to_string(),
+        KeyCode::Char('q') | KeyCode::Esc => {
+            app.hex = None;
+            app.status = "Closed hex viewer".to_string();
+        }
+        KeyCode::Left => hex.move_cursor(-1),
+        KeyCode::Right => hex.move_cursor(1),
+        KeyCode::Up => hex.move_cursor(-row),
+        KeyCode::Down => hex.move_cursor(row),
+        KeyCode::PageUp => hex.page(-1),
+        KeyCode::PageDown | KeyCode::Char(' ') => hex.page(1),
+        KeyCode::Home => hex.goto(0),
+        KeyCode::End => hex.goto(hex.size),
+        KeyCode::Char('g') => {
+            hex.input = Some(hexview::HexInput {
+                prompt: hexview::Prompt::Goto,
+                text: String::new(),
+            });
+        }
+        KeyCode::Char('/') => {
+            hex.input = Some(hexview::HexInput {
+                prompt: hexview::Prompt::Search,
+                text: String::new(),
+            });
+        }
+        KeyCode::Char('n') => app.status = search_status(hex, false, app.tasks.sender()),
+        KeyCode::Char('N') => app.status = search_status(hex, true, app.tasks.sender()),
+        _ => {}
+    }
+}
 
-    // Disable mouse capture
-    execute!(stdout(), DisableMouseCapture).expect("failed to disable mouse capture");
-    ratatui::restore();
+// Start the hex viewer's search; the outcome comes back as a task.
+fn search_status(
+    hex: &mut hexview::HexView,
+    backward: bool,
+    tx: std::sync::mpsc::Sender<tasks::TaskResult>,
+) -> String {
+    if hex.find_next(backward, tx) {
+        "Searching, Esc cancels".to_string()
+    } else {
+        "No search pattern yet, press / to search".to_string()
+    }
+}
+
+// Keys while the preview's key tree has focus: Up/Down move, Right/Left expand and
+// collapse (Left on a collapsed node jumps to its parent), Enter toggles.
+fn browse_preview(app: &mut App, code: KeyCode) {
+    let Some(preview::Preview::Data {
+        nodes, selected, ..
+    }) = app.preview.as_mut().map(|c| &mut c.preview)
+    else {
+        app.focus_preview = false;
+        return;
+    };
+    let rows = preview::structured::flatten(nodes);
+    let Some(row) = rows.get(*selected) else {
+        return;
+    };
+    match code {
+        KeyCode::Up => *selected = selected.saturating_sub(1),
+        KeyCode::Down => *selected = (*selected + 1).min(rows.len() - 1),
+        KeyCode::Home => *selected = 0,
+        KeyCode::End => *selected = rows.len() - 1,
+        KeyCode::Enter => {
+            if let Some(node) = preview::structured::node_mut(nodes, &row.idx_path) {
+                node.toggle();
+            }
+        }
+        KeyCode::Right if row.has_children && !row.is_expanded => {
+            if let Some(node) = preview::structured::node_mut(nodes, &row.idx_path) {
+                node.toggle();
+            }
+        }
+        KeyCode::Left if row.is_expanded => {
+            if let Some(node) = preview::structured::node_mut(nodes, &row.idx_path) {
+                node.toggle();
+            }
+        }
+        KeyCode::Left if row.idx_path.len() > 1 => {
+            let parent = &row.idx_path[..row.idx_path.len() - 1];
+            if let Some(i) = rows.iter().position(|r| r.idx_path == parent) {
+                *selected = i;
+            }
+        }
+        KeyCode::Tab | KeyCode::Esc => {
+            app.focus_preview = false;
+            app.status = "Browsing the list".to_string();
+        }
+        _ => {}
+    }
+}
+
+// Re-read every expanded directory with the current list options, keeping the
+// cursor on the same entry when it is still listed.
+fn reload_tree(app: &mut App) {
+    let current = app.selected_item().map(|it| it.path);
+    fs::tree::reload(&app.root, &mut app.entries, &app.list);
+    app.rebuild_view();
+    if let Some(path) = current {
+        app.selected = app.view.position(&app.entries, &path).or(Some(0));
+    }
+    clamp_selected(app);
+}
+
+// Apply the current sort mode to every loaded level, keeping the cursor on the
+// same entry.
+fn resort(app: &mut App) {
+    resort_tree(app);
+    app.status = format!("Sorted by {}", app.list.sort.key.label());
+}
+
+fn resort_tree(app: &mut App) {
+    let current = app.selected_item().map(|it| it.path);
+    fs::sort::sort_tree(&mut app.entries, &app.list.sort);
+    app.rebuild_view();
+    if let Some(path) = current {
+        app.selected = app.view.position(&app.entries, &path);
+    }
+    clamp_selected(app);
+}
+
+// Expand the tree down to `target` and select it.
+fn reveal_path(app: &mut App, target: &Path) {
+    let target = std::fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+    app.status = if select_path(app, &target) {
+        format!("Revealed {}", target.display())
+    } else {
+        format!("Could not reveal {}", target.display())
+    };
+}
+
+// Expand the tree down to `target` and select it, without a status message.
+// Returns false if it isn't in the tree.
+fn select_path(app: &mut App, target: &Path) -> bool {
+    let Some(idx) = fs::tree::reveal(&mut app.entries, target, &app.list) else {
+        return false;
+    };
+    app.rebuild_view();
+    app.selected = app.view.find(&idx);
+    true
+}
+
+// Resolve the save target from the selection and typed name. Returns the path to
+// print, or None when the name is missing or an overwrite prompt was opened.
+fn confirm_save(app: &mut App) -> Option<Vec<PathBuf>> {
+    let current = app.selected_item().filter(|it| !it.placeholder);
+    let selected = current.as_ref().map(|it| (it.path.as_path(), it.is_dir));
+    let dir = picker::SaveDialog::target_dir(&app.root, selected);
+    let save = app.save.as_mut()?;
+    if save.name.trim().is_empty() {
+        app.status = "Type a file name first".to_string();
+        return None;
+    }
+    let target = dir.join(&save.name);
+    if target.is_dir() {
+        app.status = format!("{} is a directory", target.display());
+        None
+    } else if target.exists() {
+        app.status = format!("{} exists. Overwrite? (y/n)", save.name);
+        save.confirm_overwrite = Some(target);
+        None
+    } else {
+        Some(vec![target])
+    }
 }
 
 fn build_openers_for(path: &Path) -> Vec<String> {
@@ -395,7 +1684,7 @@ fn with_node_mut<'a>(
 
 // Ensure selected is within the visible range after expand/collapse.
 fn clamp_selected(app: &mut App) {
-    let len = fs::tree::flatten(&app.entries).len();
+    let len = app.view.len();
     match (len, app.selected) {
         (0, _) => app.selected = None,
         (n, Some(i)) if i >= n => app.selected = Some(n - 1),
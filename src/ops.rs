@@ -0,0 +1,331 @@
+// This file implements the file operations available from the tree (new file, new
+// folder, rename, copy, move, trash and delete) and the prompt that collects a name
+// or a confirmation before one of them runs.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::journal::Step;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    NewFile,
+    NewDir,
+    Rename,
+    Copy,
+    Move,
+    Trash,
+    Delete, // for good, skipping the trash
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::NewFile => "New file",
+            Action::NewDir => "New folder",
+            Action::Rename => "Rename",
+            Action::Copy => "Copy",
+            Action::Move => "Move",
+            Action::Trash => "Trash",
+            Action::Delete => "Delete",
+        }
+    }
+}
+
+// An operation waiting for its name, destination or a yes/no.
+pub struct Prompt {
+    pub action: Action,
+    pub targets: Vec<PathBuf>, // entries acted on; empty when creating one
+    pub dir: PathBuf,          // where new entries go, and what relative input is based on
+    pub input: String,         // name or destination typed so far
+    pub confirming: bool,      // input is done, waiting for y/n
+}
+
+impl Prompt {
+    pub fn new(action: Action, targets: Vec<PathBuf>, dir: PathBuf) -> Self {
+        let input = match action {
+            Action::Rename => targets.first().map(|p| file_name(p)).unwrap_or_default(),
+            Action::Copy | Action::Move => dir.display().to_string(),
+            _ => String::new(),
+        };
+        Self {
+            action,
+            targets,
+            dir,
+            input,
+            confirming: matches!(action, Action::Trash | Action::Delete), // nothing to type
+        }
+    }
+
+    // What the popup asks.
+    pub fn question(&self) -> String {
+        let what = match self.targets.as_slice() {
+            [one] => file_name(one),
+            many => format!("{} entries", many.len()),
+        };
+        match self.action {
+            Action::NewFile => format!("New file in {}:", self.dir.display()),
+            Action::NewDir => format!("New folder in {}:", self.dir.display()),
+            Action::Rename => format!("Rename {} to {}? (y/n)", what, self.input),
+            Action::Copy => format!("Copy {} to:", what),
+            Action::Move => format!("Move {} to:", what),
+            Action::Trash => format!("Move {} to the trash? (y/n)", what),
+            Action::Delete => format!("Delete {} for good? (y/n)", what),
+        }
+    }
+
+    // Carry out the action. Returns the entry to select afterwards, if any. Runs
+    // over the targets in order and stops at the first failure; whatever was done
+    // by then is added to `steps` for the undo journal.
+    pub fn run(&self, steps: &mut Vec<Step>) -> io::Result<Option<PathBuf>> {
+        match self.action {
+            Action::NewFile => {
+                let path = self.dir.join(valid_name(&self.input)?);
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                steps.push(Step::Created {
+                    path: path.clone(),
+                    is_dir: false,
+                });
+                Ok(Some(path))
+            }
+            Action::NewDir => {
+                let path = self.dir.join(valid_name(&self.input)?);
+                fs::create_dir(&path)?;
+                steps.push(Step::Created {
+                    path: path.clone(),
+                    is_dir: true,
+                });
+                Ok(Some(path))
+            }
+            Action::Rename => {
+                let from = self.targets.first().ok_or(io::ErrorKind::NotFound)?;
+                let to = from.with_file_name(valid_name(&self.input)?);
+                ensure_free(&to)?;
+                fs::rename(from, &to)?;
+                steps.push(Step::Moved {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+                Ok(Some(to))
+            }
+            Action::Copy | Action::Move => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "copies and moves run as transfers",
+            )),
+            Action::Trash => {
+                for path in &self.targets {
+                    let file = crate::trash::trash(path)?;
+                    steps.push(Step::Trashed {
+                        original: path.clone(),
+                        file,
+                    });
+                }
+                Ok(None)
+            }
+            Action::Delete => {
+                for path in &self.targets {
+                    let meta = fs::symlink_metadata(path)?;
+                    if meta.is_dir() {
+                        fs::remove_dir_all(path)?;
+                    } else {
+                        fs::remove_file(path)?;
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    // Where each target of a copy or move goes, checked before the transfer starts.
+    // Clashes with existing entries are settled while it runs.
+    pub fn transfers(&self) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        let dest = self.destination()?;
+        // `..` or a symlink in the typed path can hide that it leads into a source,
+        // so the check compares where things really are
+        let real_dest = fs::canonicalize(&dest)?;
+        let mut pairs = Vec::new();
+        for src in &self.targets {
+            let name = src.file_name().ok_or(io::ErrorKind::InvalidInput)?;
+            let (to, real_to) = (dest.join(name), real_dest.join(name));
+            let real_src = real_path(src)?;
+            if real_to != real_src && real_to.starts_with(&real_src) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("can't put {} inside itself", file_name(src)),
+                ));
+            }
+            // the worker copies an entry onto itself next to it, whichever way it was typed
+            let to = if real_to == real_src { src.clone() } else { to };
+            pairs.push((src.clone(), to));
+        }
+        Ok(pairs)
+    }
+
+    // Directories whose listings the action changes.
+    pub fn affected(&self) -> Vec<PathBuf> {
+        let parents = self.targets.iter().filter_map(|p| p.parent());
+        let mut dirs: Vec<PathBuf> = match self.action {
+            Action::NewFile | Action::NewDir => vec![self.dir.clone()],
+            Action::Rename | Action::Trash | Action::Delete => {
+                parents.map(Path::to_path_buf).collect()
+            }
+            Action::Copy => self.destination().into_iter().collect(),
+            Action::Move => parents
+                .map(Path::to_path_buf)
+                .chain(self.destination().ok())
+                .collect(),
+        };
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    // Status message once the action went through.
+    pub fn done(&self) -> String {
+        let what = match self.targets.as_slice() {
+            [one] => file_name(one),
+            many => format!("{} entries", many.len()),
+        };
+        match self.action {
+            Action::NewFile => format!("Created {}", self.input),
+            Action::NewDir => format!("Created folder {}", self.input),
+            Action::Rename => format!("Renamed {} to {}", what, self.input),
+            Action::Copy => format!("Copied {} to {}", what, self.input),
+            Action::Move => format!("Moved {} to {}", what, self.input),
+            Action::Trash => format!("Moved {} to the trash", what),
+            Action::Delete => format!("Deleted {}", what),
+        }
+    }
+
+    // The typed destination directory, relative to `dir` unless absolute.
+    fn destination(&self) -> io::Result<PathBuf> {
+        let dest = self.dir.join(self.input.trim());
+        if !dest.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a folder", dest.display()),
+            ));
+        }
+        Ok(dest)
+    }
+}
+
+// `path` with its parent folder resolved, leaving the entry itself (which may be a
+// link) as it is.
+fn real_path(path: &Path) -> io::Result<PathBuf> {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            Ok(fs::canonicalize(parent)?.join(name))
+        }
+        _ => fs::canonicalize(path),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// A single path component: not empty, no separators, not "." or "..".
+fn valid_name(name: &str) -> io::Result<&str> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("\"{}\" is not a valid name", name),
+        ));
+    }
+    Ok(name)
+}
+
+// Renaming over an existing entry would silently replace it.
+pub fn ensure_free(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", file_name(path)),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "file-picker-ops-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(fs::canonicalize(&dir).unwrap())
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn transfers(
+        action: Action,
+        src: &Path,
+        dir: &Path,
+        input: &str,
+    ) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+        let mut prompt = Prompt::new(action, vec![src.to_path_buf()], dir.to_path_buf());
+        prompt.input = input.to_string();
+        prompt.transfers()
+    }
+
+    #[test]
+    fn refuses_a_folder_into_itself() {
+        let scratch = Scratch::new("itself");
+        let a = scratch.0.join("a");
+        fs::create_dir_all(a.join("sub")).unwrap();
+        fs::create_dir(scratch.0.join("b")).unwrap();
+        for input in ["a", "a/sub", "b/../a/sub", "./a/./sub/.."] {
+            for action in [Action::Copy, Action::Move] {
+                let err = transfers(action, &a, &scratch.0, input).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", input);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_a_folder_into_itself_through_a_link() {
+        let scratch = Scratch::new("link");
+        let a = scratch.0.join("a");
+        fs::create_dir_all(a.join("sub")).unwrap();
+        std::os::unix::fs::symlink(a.join("sub"), scratch.0.join("shortcut")).unwrap();
+        let err = transfers(Action::Copy, &a, &scratch.0, "shortcut").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn allows_next_to_itself_and_elsewhere() {
+        let scratch = Scratch::new("elsewhere");
+        let a = scratch.0.join("a");
+        fs::create_dir_all(a.join("sub")).unwrap();
+        fs::create_dir(scratch.0.join("b")).unwrap();
+        for input in [".", "b/.."] {
+            let pairs = transfers(Action::Copy, &a, &scratch.0, input).unwrap();
+            assert_eq!(pairs, [(a.clone(), a.clone())]);
+        }
+        let pairs = transfers(Action::Copy, &a, &scratch.0, "a/sub/../../b").unwrap();
+        assert_eq!(pairs, [(a.clone(), scratch.0.join("a/sub/../../b/a"))]);
+    }
+}
@@ -0,0 +1,237 @@
+// This file writes the paths chosen in picker mode to stdout in the format
+// requested on the command line.
+
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use clap::ValueEnum;
+use serde_json::{Value, json};
+
+use crate::fs::tree::FileNode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One path per line (or per NUL with --print0)
+    Lines,
+    /// A JSON array of objects with path, is_dir, size and mtime
+    Json,
+}
+
+pub struct OutputOptions {
+    pub format: Format,
+    pub print0: bool,
+    pub relative_to: Option<PathBuf>,
+}
+
+pub fn write_paths(
+    out: &mut impl Write,
+    paths: &[PathBuf],
+    opts: &OutputOptions,
+) -> io::Result<()> {
+    let nodes: Vec<FileNode> = paths.iter().cloned().map(FileNode::from_path).collect();
+    match opts.format {
+        Format::Lines => {
+            let sep: &[u8] = if opts.print0 { b"\0" } else { b"\n" };
+            for node in &nodes {
+                out.write_all(&path_bytes(&display_path(&node.path, opts)))?;
+                out.write_all(sep)?;
+            }
+        }
+        Format::Json => {
+            let items: Vec<Value> = nodes.iter().map(|n| node_json(n, opts)).collect();
+            serde_json::to_writer(&mut *out, &items)?;
+            out.write_all(b"\n")?;
+        }
+    }
+    out.flush()
+}
+
+fn node_json(node: &FileNode, opts: &OutputOptions) -> Value {
+    let path = display_path(&node.path, opts);
+    let mtime = node
+        .modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    let mut obj = json!({
+        "path": path.to_string_lossy(),
+        "is_dir": node.is_dir,
+        "size": node.size,
+        "mtime": mtime,
+    });
+    // JSON strings must be UTF-8; keep the exact bytes alongside the lossy string
+    if path.to_str().is_none() {
+        obj["path_bytes"] = json!(path_bytes(&path));
+    }
+    obj
+}
+
+fn display_path(path: &Path, opts: &OutputOptions) -> PathBuf {
+    match &opts.relative_to {
+        Some(base) => relative_path(path, base),
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(target_family = "unix"))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+// Express `path` relative to `base`. Both are expected to be absolute; if they
+// share no common root (e.g. different drives) the path is returned unchanged.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_parts: Vec<Component> = path.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    let common = path_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    let mut out = PathBuf::new();
+    for _ in common..base_parts.len() {
+        out.push("..");
+    }
+    for part in &path_parts[common..] {
+        out.push(part.as_os_str());
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "file-picker-output-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, name: impl AsRef<Path>, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn render(
+        paths: &[PathBuf],
+        format: Format,
+        print0: bool,
+        relative_to: Option<&Path>,
+    ) -> Vec<u8> {
+        let opts = OutputOptions {
+            format,
+            print0,
+            relative_to: relative_to.map(Path::to_path_buf),
+        };
+        let mut out = Vec::new();
+        write_paths(&mut out, paths, &opts).unwrap();
+        out
+    }
+
+    #[test]
+    fn relative_paths() {
+        let rel = |path: &str, base: &str| relative_path(Path::new(path), Path::new(base));
+        assert_eq!(rel("/a/b/c.txt", "/a"), Path::new("b/c.txt"));
+        assert_eq!(rel("/a/b/c.txt", "/a/d/e"), Path::new("../../b/c.txt"));
+        assert_eq!(rel("/a/b", "/a/b"), Path::new("."));
+        assert_eq!(rel("/a", "/a/b/c"), Path::new("../.."));
+        assert_eq!(rel("/x/y", "/a"), Path::new("../x/y"));
+        // nothing in common to anchor on
+        assert_eq!(rel("x/y", "/a"), Path::new("x/y"));
+    }
+
+    #[test]
+    fn lines_and_print0() {
+        let scratch = Scratch::new("lines");
+        let paths = [scratch.file("one", "1"), scratch.file("two words", "2")];
+        let lines = render(&paths, Format::Lines, false, None);
+        let expected = format!("{}\n{}\n", paths[0].display(), paths[1].display());
+        assert_eq!(lines, expected.as_bytes());
+
+        let nul = render(&paths, Format::Lines, true, Some(&scratch.0));
+        assert_eq!(nul, b"one\0two words\0");
+    }
+
+    #[test]
+    fn json_describes_each_path() {
+        let scratch = Scratch::new("json");
+        let file = scratch.file("data.txt", "hello");
+        let dir = scratch.0.join("sub");
+        fs::create_dir(&dir).unwrap();
+        let out = render(&[file, dir], Format::Json, false, Some(&scratch.0));
+        assert_eq!(out.last(), Some(&b'\n'));
+
+        let items: Value = serde_json::from_slice(&out).unwrap();
+        let items = items.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["path"], "data.txt");
+        assert_eq!(items[0]["is_dir"], false);
+        assert_eq!(items[0]["size"], 5);
+        assert!(items[0]["mtime"].as_u64().is_some());
+        assert!(items[0].get("path_bytes").is_none());
+        assert_eq!(items[1]["path"], "sub");
+        assert_eq!(items[1]["is_dir"], true);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn odd_names_come_out_byte_for_byte() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let scratch = Scratch::new("odd");
+        let odd = scratch.file(OsStr::from_bytes(b"caf\xe9\nline"), "x");
+        let raw = path_bytes(&odd);
+        let picked = std::slice::from_ref(&odd);
+
+        let mut expected = raw.clone();
+        expected.push(0);
+        assert_eq!(render(picked, Format::Lines, true, None), expected);
+        let relative = render(picked, Format::Lines, true, Some(&scratch.0));
+        assert_eq!(relative, b"caf\xe9\nline\0");
+
+        let items: Value =
+            serde_json::from_slice(&render(picked, Format::Json, false, None)).unwrap();
+        let bytes: Vec<u8> = items[0]["path_bytes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b.as_u64().unwrap() as u8)
+            .collect();
+        assert_eq!(bytes, raw);
+        assert_eq!(Path::new(OsStr::from_bytes(&bytes)), odd);
+        assert_eq!(items[0]["path"], odd.to_string_lossy().as_ref());
+    }
+}
@@ -0,0 +1,55 @@
+// This file describes the dialog modes the picker can run in and what each of them
+// is allowed to confirm.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Open,      // pick existing files
+    Directory, // pick existing directories
+    Save,      // choose a directory and type a file name to create
+}
+
+impl Mode {
+    // Whether an entry of this kind can be confirmed (or marked) in this mode.
+    pub fn accepts(self, is_dir: bool) -> bool {
+        match self {
+            Mode::Open => !is_dir,
+            Mode::Directory => is_dir,
+            Mode::Save => true,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Mode::Open => "files",
+            Mode::Directory => "directories",
+            Mode::Save => "a save location",
+        }
+    }
+}
+
+// State of the file name input shown under the tree in save mode.
+pub struct SaveDialog {
+    pub name: String,
+    pub confirm_overwrite: Option<PathBuf>, // set while the overwrite prompt is open
+}
+
+impl SaveDialog {
+    pub fn new(default_name: String) -> Self {
+        Self {
+            name: default_name,
+            confirm_overwrite: None,
+        }
+    }
+
+    // The directory a file would be saved into: the selected directory itself, or
+    // the parent of the selected file, or the root when nothing is selected.
+    pub fn target_dir(root: &Path, selected: Option<(&Path, bool)>) -> PathBuf {
+        match selected {
+            Some((path, true)) => path.to_path_buf(),
+            Some((path, false)) => path.parent().unwrap_or(root).to_path_buf(),
+            None => root.to_path_buf(),
+        }
+    }
+}
@@ -0,0 +1,557 @@
+// This file implements a small, dependency-free syntax highlighter for the preview
+// pane. It is a line-based tokenizer with just enough per-language knowledge
+// (keywords, comments, strings, keys) to make source readable at a glance.
+
+use std::path::Path;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Rust,
+    Toml,
+    Markdown,
+    Json,
+    Yaml,
+    Shell,
+    Python,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Plain,
+    Keyword,
+    Literal, // true/false/null and friends
+    String,
+    Number,
+    Comment,
+    Key,       // map keys, variables
+    Attribute, // attributes, decorators, macros
+    Heading,
+    Code, // inline or fenced code in Markdown
+}
+
+// Theme colors for each token kind.
+fn style(kind: Kind) -> Style {
+    let s = Style::default();
+    match kind {
+        Kind::Plain => s,
+        Kind::Keyword => s.fg(Color::Magenta),
+        Kind::Literal => s.fg(Color::LightYellow),
+        Kind::String => s.fg(Color::Green),
+        Kind::Number => s.fg(Color::Yellow),
+        Kind::Comment => s.fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        Kind::Key => s.fg(Color::Cyan),
+        Kind::Attribute => s.fg(Color::LightBlue),
+        Kind::Heading => s.fg(Color::Blue).add_modifier(Modifier::BOLD),
+        Kind::Code => s.fg(Color::LightRed),
+    }
+}
+
+struct Syntax {
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    strings: &'static [&'static str], // longest delimiters first
+}
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    literals: &["true", "false", "None", "Some", "Ok", "Err"],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    strings: &["\""],
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+        "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+        "yield", "self",
+    ],
+    literals: &["True", "False", "None"],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &["\"\"\"", "'''", "\"", "'"],
+};
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "export", "local", "readonly", "set", "unset", "shift", "exit",
+        "source",
+    ],
+    literals: &["true", "false"],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &["\"", "'"],
+};
+
+const TOML: Syntax = Syntax {
+    keywords: &[],
+    literals: &["true", "false"],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &["\"\"\"", "'''", "\"", "'"],
+};
+
+const YAML: Syntax = Syntax {
+    keywords: &[],
+    literals: &["true", "false", "null", "yes", "no", "on", "off", "~"],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &["\"", "'"],
+};
+
+const JSON: Syntax = Syntax {
+    keywords: &[],
+    literals: &["true", "false", "null"],
+    line_comment: None,
+    block_comment: None,
+    strings: &["\""],
+};
+
+pub fn detect(path: &Path, first_line: Option<&str>) -> Option<Lang> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    let by_ext = ext.as_deref().and_then(from_name);
+    if by_ext.is_some() {
+        return by_ext;
+    }
+    if path.file_name().is_some_and(|n| n == "Cargo.lock") {
+        return Some(Lang::Toml);
+    }
+
+    // e.g. "#!/usr/bin/env python3" or "#!/bin/bash"
+    let shebang = first_line?.strip_prefix("#!")?;
+    let mut words = shebang.split_whitespace();
+    let mut prog = words.next()?.rsplit('/').next()?;
+    if prog == "env" {
+        prog = words.find(|w| !w.starts_with('-'))?;
+    }
+    if prog.starts_with("python") {
+        Some(Lang::Python)
+    } else if matches!(prog, "sh" | "bash" | "zsh" | "dash" | "ksh") {
+        Some(Lang::Shell)
+    } else {
+        None
+    }
+}
+
+// A language from a file extension or a code fence tag like "rust" or "bash".
+pub fn from_name(name: &str) -> Option<Lang> {
+    match name.to_ascii_lowercase().as_str() {
+        "rs" | "rust" => Some(Lang::Rust),
+        "toml" => Some(Lang::Toml),
+        "md" | "markdown" => Some(Lang::Markdown),
+        "json" | "jsonc" => Some(Lang::Json),
+        "yaml" | "yml" => Some(Lang::Yaml),
+        "sh" | "bash" | "zsh" | "shell" | "console" => Some(Lang::Shell),
+        "py" | "pyw" | "python" => Some(Lang::Python),
+        _ => None,
+    }
+}
+
+// Multi-line constructs that carry over from one line to the next.
+#[derive(Default)]
+struct State {
+    block_comment: bool,
+    string: Option<&'static str>, // open multi-line string delimiter
+    fence: bool,                  // inside a Markdown code fence
+}
+
+pub fn highlight(lang: Lang, lines: &[String]) -> Vec<Line<'static>> {
+    let mut state = State::default();
+    lines
+        .iter()
+        .map(|line| {
+            let spans = match lang {
+                Lang::Markdown => markdown_line(line, &mut state),
+                Lang::Rust => tokenize(line, &RUST, lang, &mut state),
+                Lang::Python => tokenize(line, &PYTHON, lang, &mut state),
+                Lang::Shell => tokenize(line, &SHELL, lang, &mut state),
+                Lang::Json => tokenize(line, &JSON, lang, &mut state),
+                Lang::Toml => toml_line(line, &mut state),
+                Lang::Yaml => yaml_line(line, &mut state),
+            };
+            Line::from(spans)
+        })
+        .collect()
+}
+
+struct Out {
+    spans: Vec<Span<'static>>,
+}
+
+impl Out {
+    fn push(&mut self, text: &[char], kind: Kind) {
+        if !text.is_empty() {
+            self.spans
+                .push(Span::styled(text.iter().collect::<String>(), style(kind)));
+        }
+    }
+}
+
+fn starts_with(chars: &[char], at: usize, pat: &str) -> bool {
+    pat.chars()
+        .enumerate()
+        .all(|(k, p)| chars.get(at + k) == Some(&p))
+}
+
+// Index just past the closing `delim` at or after `from`, honoring backslash
+// escapes for single-char delimiters.
+fn find_close(chars: &[char], from: usize, delim: &str) -> Option<usize> {
+    let single = delim.chars().count() == 1;
+    let mut i = from;
+    while i < chars.len() {
+        if single && chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if starts_with(chars, i, delim) {
+            return Some(i + delim.chars().count());
+        }
+        i += 1;
+    }
+    None
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn next_non_space(chars: &[char], from: usize) -> Option<char> {
+    chars[from.min(chars.len())..]
+        .iter()
+        .copied()
+        .find(|c| !c.is_whitespace())
+}
+
+fn tokenize(line: &str, syn: &Syntax, lang: Lang, state: &mut State) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = Out { spans: Vec::new() };
+    tokenize_chars(&chars, 0, syn, lang, state, &mut out);
+    out.spans
+}
+
+fn tokenize_chars(
+    chars: &[char],
+    start: usize,
+    syn: &Syntax,
+    lang: Lang,
+    state: &mut State,
+    out: &mut Out,
+) {
+    let n = chars.len();
+    let mut i = start;
+    let mut plain_from = i;
+    macro_rules! flush {
+        () => {
+            out.push(&chars[plain_from..i], Kind::Plain);
+        };
+    }
+
+    while i < n {
+        // continuation of constructs opened on an earlier line
+        if state.block_comment {
+            let (open, close) = syn.block_comment.unwrap_or(("/*", "*/"));
+            let body = if starts_with(chars, i, open) {
+                i + open.len()
+            } else {
+                i
+            };
+            let end = find_close(chars, body, close);
+            state.block_comment = end.is_none();
+            let end = end.unwrap_or(n);
+            out.push(&chars[i..end], Kind::Comment);
+            i = end;
+            plain_from = i;
+            continue;
+        }
+        if let Some(delim) = state.string {
+            let end = find_close(chars, i, delim);
+            out.push(&chars[i..end.unwrap_or(n)], Kind::String);
+            state.string = if end.is_some() { None } else { Some(delim) };
+            i = end.unwrap_or(n);
+            plain_from = i;
+            continue;
+        }
+
+        let c = chars[i];
+        if let Some(lc) = syn.line_comment
+            && starts_with(chars, i, lc)
+            // in shell, `#` only starts a comment at the beginning of a word
+            && (lang != Lang::Shell || i == 0 || chars[i - 1].is_whitespace())
+        {
+            flush!();
+            out.push(&chars[i..], Kind::Comment);
+            return;
+        }
+        if let Some((open, _)) = syn.block_comment
+            && starts_with(chars, i, open)
+        {
+            flush!();
+            state.block_comment = true;
+            plain_from = i;
+            continue;
+        }
+        if let Some(delim) = syn
+            .strings
+            .iter()
+            .copied()
+            .find(|d| starts_with(chars, i, d))
+        {
+            flush!();
+            let body = i + delim.chars().count();
+            let end = find_close(chars, body, delim);
+            if end.is_none() && delim.len() == 3 {
+                state.string = Some(delim);
+            }
+            let end = end.unwrap_or(n);
+            // a string followed by `:` is a key in JSON
+            let kind = if lang == Lang::Json && next_non_space(chars, end) == Some(':') {
+                Kind::Key
+            } else {
+                Kind::String
+            };
+            out.push(&chars[i..end], kind);
+            i = end;
+            plain_from = i;
+            continue;
+        }
+        if lang == Lang::Rust && c == '\'' {
+            // char literal like 'a' or '\n'; anything else is a lifetime
+            let end = if chars.get(i + 1) == Some(&'\\') {
+                chars[i + 2..]
+                    .iter()
+                    .position(|&c| c == '\'')
+                    .map(|p| i + 3 + p)
+            } else if chars.get(i + 2) == Some(&'\'') {
+                Some(i + 3)
+            } else {
+                None
+            };
+            if let Some(end) = end {
+                flush!();
+                out.push(&chars[i..end], Kind::String);
+                i = end;
+                plain_from = i;
+                continue;
+            }
+        }
+        if (lang == Lang::Rust && c == '#' && chars.get(i + 1) == Some(&'['))
+            || (lang == Lang::Python && c == '@' && i == first_non_space(chars))
+        {
+            flush!();
+            out.push(&chars[i..], Kind::Attribute);
+            return;
+        }
+        if lang == Lang::Shell && c == '$' {
+            flush!();
+            let end = if chars.get(i + 1) == Some(&'{') {
+                find_close(chars, i + 2, "}").unwrap_or(n)
+            } else {
+                let mut j = i + 1;
+                while j < n && (is_ident(chars[j]) || (j == i + 1 && "@*#?$!".contains(chars[j]))) {
+                    j += 1;
+                }
+                j
+            };
+            out.push(&chars[i..end], Kind::Key);
+            i = end;
+            plain_from = i;
+            continue;
+        }
+        if c.is_ascii_digit()
+            || (c == '-'
+                && chars.get(i + 1).is_some_and(char::is_ascii_digit)
+                && lang == Lang::Json)
+        {
+            flush!();
+            let mut j = i + 1;
+            while j < n && (chars[j].is_ascii_alphanumeric() || chars[j] == '_' || chars[j] == '.')
+            {
+                j += 1;
+            }
+            out.push(&chars[i..j], Kind::Number);
+            i = j;
+            plain_from = i;
+            continue;
+        }
+        if is_ident(c) {
+            let mut j = i + 1;
+            while j < n && is_ident(chars[j]) {
+                j += 1;
+            }
+            let word: String = chars[i..j].iter().collect();
+            let kind = if syn.keywords.contains(&word.as_str()) {
+                Some(Kind::Keyword)
+            } else if syn.literals.contains(&word.as_str()) {
+                Some(Kind::Literal)
+            } else if lang == Lang::Rust && chars.get(j) == Some(&'!') {
+                j += 1; // macro invocation
+                Some(Kind::Attribute)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                flush!();
+                out.push(&chars[i..j], kind);
+                plain_from = j;
+            }
+            i = j;
+            continue;
+        }
+        i += 1;
+    }
+    flush!();
+}
+
+fn first_non_space(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .position(|c| !c.is_whitespace())
+        .unwrap_or(chars.len())
+}
+
+fn toml_line(line: &str, state: &mut State) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = Out { spans: Vec::new() };
+    let lead = first_non_space(&chars);
+    if state.string.is_none() {
+        // [table] and [[array]] headers
+        if chars.get(lead) == Some(&'[') {
+            out.push(&chars[..lead], Kind::Plain);
+            let end = chars
+                .iter()
+                .rposition(|&c| c == ']')
+                .map_or(chars.len(), |p| p + 1);
+            out.push(&chars[lead..end], Kind::Heading);
+            tokenize_chars(&chars, end, &TOML, Lang::Toml, state, &mut out);
+            return out.spans;
+        }
+        // key = value
+        if let Some(eq) = chars.iter().position(|&c| c == '=')
+            && !chars[..eq].iter().any(|&c| c == '"' || c == '#')
+        {
+            out.push(&chars[..eq], Kind::Key);
+            out.push(&chars[eq..eq + 1], Kind::Plain);
+            tokenize_chars(&chars, eq + 1, &TOML, Lang::Toml, state, &mut out);
+            return out.spans;
+        }
+    }
+    tokenize_chars(&chars, 0, &TOML, Lang::Toml, state, &mut out);
+    out.spans
+}
+
+fn yaml_line(line: &str, state: &mut State) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = Out { spans: Vec::new() };
+    let mut lead = first_non_space(&chars);
+    if starts_with(&chars, lead, "- ") {
+        lead += 2;
+    }
+    if starts_with(&chars, lead, "---") || starts_with(&chars, lead, "...") {
+        out.push(&chars, Kind::Comment);
+        return out.spans;
+    }
+    // `key:` followed by a space or the end of the line
+    let key_end = (lead..chars.len())
+        .find(|&i| chars[i] == ':' && chars.get(i + 1).is_none_or(|c| c.is_whitespace()));
+    match key_end {
+        Some(colon) if chars[lead] != '#' && chars[lead] != '"' && chars[lead] != '\'' => {
+            out.push(&chars[..lead], Kind::Plain);
+            out.push(&chars[lead..colon], Kind::Key);
+            out.push(&chars[colon..colon + 1], Kind::Plain);
+            tokenize_chars(&chars, colon + 1, &YAML, Lang::Yaml, state, &mut out);
+        }
+        _ => tokenize_chars(&chars, 0, &YAML, Lang::Yaml, state, &mut out),
+    }
+    out.spans
+}
+
+fn markdown_line(line: &str, state: &mut State) -> Vec<Span<'static>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = Out { spans: Vec::new() };
+    let lead = first_non_space(&chars);
+    if starts_with(&chars, lead, "```") || starts_with(&chars, lead, "~~~") {
+        state.fence = !state.fence;
+        out.push(&chars, Kind::Comment);
+        return out.spans;
+    }
+    if state.fence {
+        out.push(&chars, Kind::Code);
+        return out.spans;
+    }
+    if chars.get(lead) == Some(&'#') {
+        out.push(&chars, Kind::Heading);
+        return out.spans;
+    }
+    if chars.get(lead) == Some(&'>') {
+        out.push(&chars, Kind::Comment);
+        return out.spans;
+    }
+
+    let mut i = 0;
+    // list bullets and numbers
+    let bullet_end =
+        if matches!(chars.get(lead), Some('-' | '*' | '+')) && chars.get(lead + 1) == Some(&' ') {
+            Some(lead + 1)
+        } else {
+            let digits = chars[lead..]
+                .iter()
+                .take_while(|c| c.is_ascii_digit())
+                .count();
+            (digits > 0 && chars.get(lead + digits) == Some(&'.')).then_some(lead + digits + 1)
+        };
+    if let Some(end) = bullet_end {
+        out.push(&chars[..end], Kind::Keyword);
+        i = end;
+    }
+
+    let mut plain_from = i;
+    while i < chars.len() {
+        let c = chars[i];
+        let span = match c {
+            '`' => find_close(&chars, i + 1, "`").map(|end| (end, Kind::Code)),
+            '*' | '_' => {
+                let delim = if chars.get(i + 1) == Some(&c) { 2 } else { 1 };
+                let pat: String = std::iter::repeat_n(c, delim).collect();
+                find_close(&chars, i + delim, &pat)
+                    .filter(|&end| end > i + 2 * delim)
+                    .map(|end| (end, Kind::Literal))
+            }
+            '[' => {
+                // [text](url)
+                let close = find_close(&chars, i + 1, "]");
+                close
+                    .filter(|&c| chars.get(c) == Some(&'('))
+                    .and_then(|c| find_close(&chars, c + 1, ")"))
+                    .map(|end| (end, Kind::Key))
+            }
+            _ => None,
+        };
+        match span {
+            Some((end, kind)) => {
+                out.push(&chars[plain_from..i], Kind::Plain);
+                out.push(&chars[i..end], kind);
+                i = end;
+                plain_from = i;
+            }
+            None => i += 1,
+        }
+    }
+    out.push(&chars[plain_from..], Kind::Plain);
+    out.spans
+}
@@ -0,0 +1,344 @@
+// This file renders Markdown for the preview pane: headings, emphasis, inline code,
+// lists, quotes, fenced code blocks and links are drawn styled instead of as raw
+// source. It is line based like the highlighter, which is plenty for READMEs.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+use super::highlight;
+
+pub fn render(lines: &[String]) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut out = Vec::new();
+    let mut fence: Option<(String, String)> = None; // closing marker, language tag
+    let mut code: Vec<String> = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        i += 1;
+        let trimmed = line.trim_start();
+        let lead = line.len() - trimmed.len();
+
+        if let Some((marker, lang)) = &fence {
+            let closes = fence_marker(trimmed.trim_end())
+                .is_some_and(|m| m.starts_with(marker.as_str()) && trimmed.trim_end() == m);
+            if lead < 4 && closes {
+                out.extend(code_block(lang, &code));
+                code.clear();
+                fence = None;
+            } else {
+                code.push(line.clone());
+            }
+            continue;
+        }
+        if lead < 4
+            && let Some(marker) = fence_marker(trimmed)
+        {
+            let lang = trimmed[marker.len()..].trim().to_string();
+            if !lang.is_empty() {
+                out.push(Line::styled(
+                    format!("  {}", lang),
+                    dim.add_modifier(Modifier::ITALIC),
+                ));
+            }
+            fence = Some((marker, lang));
+            continue;
+        }
+
+        if let Some((level, text)) = atx_heading(trimmed) {
+            out.push(heading(level, text));
+            continue;
+        }
+        if is_rule(trimmed) {
+            out.push(Line::styled("─".repeat(40), dim));
+            continue;
+        }
+        // "Title" underlined by === or --- on the next line
+        if !trimmed.is_empty()
+            && let Some(level) = lines.get(i).and_then(|next| setext_level(next))
+            && block_prefix(trimmed).is_none()
+        {
+            out.push(heading(level, trimmed.trim_end()));
+            i += 1;
+            continue;
+        }
+
+        let mut spans = vec![Span::raw(" ".repeat(lead))];
+        let mut base = Style::default();
+        let mut rest = trimmed;
+        match block_prefix(trimmed) {
+            Some(Block::Quote(text)) => {
+                spans.push(Span::styled("│ ", dim));
+                base = base.fg(Color::Gray).add_modifier(Modifier::ITALIC);
+                rest = text;
+            }
+            Some(Block::Bullet(text)) => {
+                let bullet = ["•", "◦", "▪"][(lead / 2) % 3];
+                spans.push(Span::styled(format!("{} ", bullet), bullet_style()));
+                rest = text;
+                // task list items
+                if let Some(t) = rest.strip_prefix("[ ] ") {
+                    spans.push(Span::styled("☐ ", bullet_style()));
+                    rest = t;
+                } else if let Some(t) = rest
+                    .strip_prefix("[x] ")
+                    .or_else(|| rest.strip_prefix("[X] "))
+                {
+                    spans.push(Span::styled("☑ ", bullet_style()));
+                    rest = t;
+                }
+            }
+            Some(Block::Numbered(number, text)) => {
+                spans.push(Span::styled(format!("{} ", number), bullet_style()));
+                rest = text;
+            }
+            None => {}
+        }
+        let chars: Vec<char> = rest.chars().collect();
+        inline(&chars, base, &mut spans);
+        out.push(Line::from(spans));
+    }
+    // a fence left open by the end of the preview still shows its code
+    if let Some((_, lang)) = &fence {
+        out.extend(code_block(lang, &code));
+    }
+    out
+}
+
+fn bullet_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn heading(level: usize, text: &str) -> Line<'static> {
+    let base = match level {
+        1 => Style::default()
+            .fg(Color::Blue)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        2 => Style::default()
+            .fg(Color::Blue)
+            .add_modifier(Modifier::BOLD),
+        _ => Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    inline(&chars, base, &mut spans);
+    Line::from(spans)
+}
+
+// Code lines behind a gutter, highlighted when the fence names a known language.
+fn code_block(lang: &str, code: &[String]) -> Vec<Line<'static>> {
+    let gutter = Span::styled("▏ ", Style::default().fg(Color::DarkGray));
+    let lines = match highlight::from_name(lang.split_whitespace().next().unwrap_or("")) {
+        Some(lang) => highlight::highlight(lang, code),
+        None => code
+            .iter()
+            .map(|l| Line::styled(l.clone(), Style::default().fg(Color::LightRed)))
+            .collect(),
+    };
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut spans = vec![gutter.clone()];
+            spans.extend(line.spans);
+            Line::from(spans)
+        })
+        .collect()
+}
+
+// "```" or "~~~" (or longer) opening a code fence.
+fn fence_marker(line: &str) -> Option<String> {
+    let c = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let n = line.chars().take_while(|&x| x == c).count();
+    (n >= 3).then(|| c.to_string().repeat(n))
+}
+
+fn atx_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None; // "#hashtag"
+    }
+    // closing hashes are decoration: "## Title ##"
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    Some((level, text))
+}
+
+fn setext_level(line: &str) -> Option<usize> {
+    let t = line.trim();
+    if t.len() >= 2 && t.chars().all(|c| c == '=') {
+        Some(1)
+    } else if t.len() >= 2 && t.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+// "---", "***" or "___", optionally spaced out.
+fn is_rule(line: &str) -> bool {
+    let t: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    t.len() >= 3 && ['-', '*', '_'].iter().any(|&c| t.chars().all(|x| x == c))
+}
+
+enum Block<'a> {
+    Quote(&'a str),
+    Bullet(&'a str),
+    Numbered(&'a str, &'a str), // "1." and the item text
+}
+
+fn block_prefix(line: &str) -> Option<Block<'_>> {
+    if let Some(rest) = line.strip_prefix('>') {
+        return Some(Block::Quote(rest.strip_prefix(' ').unwrap_or(rest)));
+    }
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return Some(Block::Bullet(rest));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && digits < 10 {
+        let after = &line[digits..];
+        if after.starts_with(". ") || after.starts_with(") ") {
+            return Some(Block::Numbered(&line[..digits + 1], &after[2..]));
+        }
+    }
+    None
+}
+
+// Inline markup: `code`, *emphasis*, **strong**, ~~strike~~, [links](url),
+// ![images](url), <autolinks> and backslash escapes.
+fn inline(chars: &[char], base: Style, out: &mut Vec<Span<'static>>) {
+    let mut plain = String::new();
+    let flush = |plain: &mut String, out: &mut Vec<Span<'static>>| {
+        if !plain.is_empty() {
+            out.push(Span::styled(std::mem::take(plain), base));
+        }
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                plain.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                let n = run(chars, i, '`');
+                if let Some(close) = find(chars, i + n, &vec!['`'; n]) {
+                    flush(&mut plain, out);
+                    let text: String = chars[i + n..close].iter().collect();
+                    let code_style = base.fg(Color::LightRed).remove_modifier(Modifier::ITALIC);
+                    out.push(Span::styled(text.trim().to_string(), code_style));
+                    i = close + n;
+                    continue;
+                }
+            }
+            '*' | '_' | '~' => {
+                let n = run(chars, i, c).min(2);
+                // "snake_case" and "2 * 3" aren't emphasis
+                let intraword = c == '_' && i > 0 && chars[i - 1].is_alphanumeric();
+                let opens = chars.get(i + n).is_some_and(|x| !x.is_whitespace());
+                if !intraword
+                    && opens
+                    && (c != '~' || n == 2)
+                    && let Some(close) = find(chars, i + n, &vec![c; n])
+                    && close > i + n
+                {
+                    flush(&mut plain, out);
+                    let style = match (c, n) {
+                        ('~', _) => base.add_modifier(Modifier::CROSSED_OUT),
+                        (_, 2) => base.add_modifier(Modifier::BOLD),
+                        _ => base.add_modifier(Modifier::ITALIC),
+                    };
+                    inline(&chars[i + n..close], style, out);
+                    i = close + n;
+                    continue;
+                }
+            }
+            '!' | '[' => {
+                let start = if c == '!' { i + 1 } else { i };
+                if let Some((text, url, end)) = link(chars, start) {
+                    flush(&mut plain, out);
+                    if c == '!' {
+                        out.push(Span::styled("🖼 ", base));
+                    }
+                    let link_style = base.fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+                    inline(text, link_style, out);
+                    if !url.is_empty() {
+                        out.push(Span::styled(
+                            format!(" ({})", url),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    i = end;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(close) = find(chars, i + 1, &['>'])
+                    && chars[i + 1..close]
+                        .iter()
+                        .collect::<String>()
+                        .contains("://")
+                {
+                    flush(&mut plain, out);
+                    let url: String = chars[i + 1..close].iter().collect();
+                    out.push(Span::styled(
+                        url,
+                        base.fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+                    ));
+                    i = close + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut plain, out);
+}
+
+fn run(chars: &[char], from: usize, c: char) -> usize {
+    chars[from..].iter().take_while(|&&x| x == c).count()
+}
+
+// Start of the next occurrence of `pat` at or after `from`.
+fn find(chars: &[char], from: usize, pat: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&j| chars[j..].starts_with(pat))
+}
+
+// "[text](url)" starting at `from`: the text, the URL (without a title) and the
+// index just past the closing parenthesis.
+fn link(chars: &[char], from: usize) -> Option<(&[char], String, usize)> {
+    if chars.get(from) != Some(&'[') {
+        return None;
+    }
+    let mut depth = 0;
+    let close = (from..chars.len()).find(|&j| {
+        match chars[j] {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        depth == 0
+    })?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, &[')'])?;
+    let target: String = chars[close + 2..end].iter().collect();
+    // [text](url "title")
+    let url = target.split_whitespace().next().unwrap_or("").to_string();
+    Some((&chars[from + 1..close], url, end + 1))
+}
@@ -0,0 +1,202 @@
+// This file builds the preview shown beside the tree for the selected entry. Only a
+// bounded prefix of a file is ever read, so huge files are safe to select.
+
+pub mod highlight;
+pub mod markdown;
+pub mod structured;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use ratatui::text::Line;
+
+use crate::fs::filter::ListOptions;
+use crate::fs::tree::FileNode;
+
+use structured::{DataFormat, DataNode, ParseError, Table};
+
+const MAX_BYTES: u64 = 64 * 1024; // prefix read for text previews
+const MAX_LINES: usize = 500;
+const HEX_BYTES: usize = 512; // prefix shown for binary files
+const MAX_DIR_ENTRIES: usize = 500;
+const MAX_DATA_BYTES: u64 = 1024 * 1024; // key trees need the whole document
+const MAX_TABLE_ROWS: usize = 500;
+
+pub enum Preview {
+    Text {
+        lines: Vec<Line<'static>>, // syntax highlighted when the language is known
+        truncated: bool,           // more content follows what was read
+    },
+    Directory {
+        entries: Vec<(String, bool)>, // name, is_dir
+        dirs: usize,
+        files: usize,
+    },
+    Binary {
+        bytes: Vec<u8>,
+        size: u64,
+    },
+    Data {
+        format: DataFormat,
+        nodes: Vec<DataNode>,
+        selected: usize, // row in the flattened key tree
+    },
+    Table {
+        format: DataFormat,
+        table: Table,
+        truncated: bool,
+    },
+    Invalid {
+        format: DataFormat,
+        error: ParseError,
+        lines: Vec<Line<'static>>, // the source, to show the error in context
+    },
+    Error(String),
+    Loading, // being read on a worker
+}
+
+// The preview for one path, kept until the selection moves elsewhere.
+pub struct PreviewCache {
+    pub path: PathBuf,
+    pub preview: Preview,
+}
+
+pub fn load(path: &Path, is_dir: bool, opts: &ListOptions) -> Preview {
+    let res = if is_dir {
+        Ok(load_dir(path, opts))
+    } else {
+        load_file(path)
+    };
+    res.unwrap_or_else(|e| Preview::Error(e.to_string()))
+}
+
+fn load_dir(path: &Path, opts: &ListOptions) -> Preview {
+    let nodes = FileNode::read_directory(path, opts);
+    let dirs = nodes.iter().filter(|n| n.is_dir).count();
+    let files = nodes.len() - dirs;
+    let entries = nodes
+        .into_iter()
+        .take(MAX_DIR_ENTRIES)
+        .map(|n| (n.name, n.is_dir))
+        .collect();
+    Preview::Directory {
+        entries,
+        dirs,
+        files,
+    }
+}
+
+fn load_file(path: &Path) -> io::Result<Preview> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    // documents small enough to parse whole get a structured view
+    let format = DataFormat::detect(path)
+        .filter(|f| matches!(f, DataFormat::Csv | DataFormat::Tsv) || size <= MAX_DATA_BYTES);
+    let limit = match format {
+        Some(DataFormat::Json | DataFormat::Yaml | DataFormat::Toml) => MAX_DATA_BYTES,
+        _ => MAX_BYTES,
+    };
+    let mut buf = Vec::new();
+    file.take(limit).read_to_end(&mut buf)?;
+
+    if is_binary(&buf) {
+        buf.truncate(HEX_BYTES);
+        return Ok(Preview::Binary { bytes: buf, size });
+    }
+    if let Some(format) = format {
+        let text = String::from_utf8_lossy(&buf);
+        let complete = size <= buf.len() as u64;
+        let parsed = match format {
+            DataFormat::Csv | DataFormat::Tsv => {
+                structured::parse_table(format, &text, complete, MAX_TABLE_ROWS).map(|table| {
+                    Preview::Table {
+                        format,
+                        truncated: !complete || table.rows.len() == MAX_TABLE_ROWS,
+                        table,
+                    }
+                })
+            }
+            _ => structured::parse_tree(format, &text).map(|nodes| Preview::Data {
+                format,
+                nodes,
+                selected: 0,
+            }),
+        };
+        return Ok(parsed.unwrap_or_else(|error| {
+            let raw: Vec<String> = text
+                .lines()
+                .take(MAX_LINES)
+                .map(|l| l.replace('\t', "    "))
+                .collect();
+            let lines = match highlight::detect(path, None) {
+                Some(lang) => highlight::highlight(lang, &raw),
+                None => raw.into_iter().map(Line::raw).collect(),
+            };
+            Preview::Invalid {
+                format,
+                error,
+                lines,
+            }
+        }));
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let mut raw: Vec<String> = text.lines().map(|l| l.replace('\t', "    ")).collect();
+    let mut truncated = size > buf.len() as u64;
+    if raw.len() > MAX_LINES {
+        raw.truncate(MAX_LINES);
+        truncated = true;
+    }
+    let lines = match highlight::detect(path, raw.first().map(String::as_str)) {
+        Some(highlight::Lang::Markdown) => markdown::render(&raw),
+        Some(lang) => highlight::highlight(lang, &raw),
+        None => raw.into_iter().map(Line::raw).collect(),
+    };
+    Ok(Preview::Text { lines, truncated })
+}
+
+// NUL bytes or invalid UTF-8 mean binary. A multi-byte char cut off by the end of
+// the prefix doesn't count.
+fn is_binary(buf: &[u8]) -> bool {
+    if buf.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(buf) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+// Classic hex dump rows: offset, 16 hex bytes and their printable ASCII.
+pub fn hex_lines(bytes: &[u8], base_offset: u64) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::with_capacity(49);
+            for (j, b) in chunk.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", b));
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<49} |{}|",
+                base_offset + (i * 16) as u64,
+                hex,
+                ascii
+            )
+        })
+        .collect()
+}
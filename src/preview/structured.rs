@@ -0,0 +1,411 @@
+// This file parses data files (JSON, YAML, TOML, CSV/TSV) for the preview pane. Key
+// trees mirror `FileNode`: each node can be expanded or collapsed and is flattened
+// into rows for drawing.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+    Tsv,
+}
+
+impl DataFormat {
+    pub fn detect(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Some(DataFormat::Json),
+            "yaml" | "yml" => Some(DataFormat::Yaml),
+            "toml" => Some(DataFormat::Toml),
+            "csv" => Some(DataFormat::Csv),
+            "tsv" | "tab" => Some(DataFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DataFormat::Json => "JSON",
+            DataFormat::Yaml => "YAML",
+            DataFormat::Toml => "TOML",
+            DataFormat::Csv => "CSV",
+            DataFormat::Tsv => "TSV",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DataNode {
+    pub key: String,
+    pub value: String, // scalar text, or a summary like "{3}" / "[5]" for containers
+    pub children: Vec<DataNode>,
+    pub is_expanded: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataRow {
+    pub idx_path: Vec<usize>, // path of indices from root to this node
+    pub key: String,
+    pub value: String,
+    pub has_children: bool,
+    pub is_expanded: bool,
+    pub indent: u16,
+}
+
+impl DataNode {
+    fn leaf(key: String, value: String) -> Self {
+        Self {
+            key,
+            value,
+            children: Vec::new(),
+            is_expanded: false,
+        }
+    }
+
+    fn branch(key: String, open: char, close: char, children: Vec<DataNode>) -> Self {
+        Self {
+            key,
+            value: format!("{}{}{}", open, children.len(), close),
+            children,
+            is_expanded: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if !self.children.is_empty() {
+            self.is_expanded = !self.is_expanded;
+        }
+    }
+}
+
+pub fn flatten(nodes: &[DataNode]) -> Vec<DataRow> {
+    fn walk(out: &mut Vec<DataRow>, nodes: &[DataNode], prefix: &[usize], indent: u16) {
+        for (i, node) in nodes.iter().enumerate() {
+            let mut idx_path = prefix.to_vec();
+            idx_path.push(i);
+            out.push(DataRow {
+                idx_path: idx_path.clone(),
+                key: node.key.clone(),
+                value: node.value.clone(),
+                has_children: !node.children.is_empty(),
+                is_expanded: node.is_expanded,
+                indent,
+            });
+            if node.is_expanded {
+                walk(out, &node.children, &idx_path, indent + 1);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(&mut out, nodes, &[], 0);
+    out
+}
+
+pub fn node_mut<'a>(nodes: &'a mut [DataNode], idx_path: &[usize]) -> Option<&'a mut DataNode> {
+    let (first, rest) = idx_path.split_first()?;
+    let node = nodes.get_mut(*first)?;
+    if rest.is_empty() {
+        Some(node)
+    } else {
+        node_mut(&mut node.children, rest)
+    }
+}
+
+// Where parsing failed; line and column are 1-based.
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+// Parse a key/value document into top-level nodes, with the first level expanded.
+pub fn parse_tree(format: DataFormat, text: &str) -> Result<Vec<DataNode>, ParseError> {
+    let mut nodes = match format {
+        DataFormat::Json => {
+            let value: serde_json::Value = serde_json::from_str(text).map_err(|e| ParseError {
+                message: strip_location(e.to_string()),
+                line: e.line(),
+                column: e.column(),
+            })?;
+            top_level(from_json("(root)".to_string(), value))
+        }
+        DataFormat::Yaml => {
+            let mut docs = Vec::new();
+            for doc in serde_yaml::Deserializer::from_str(text) {
+                let value = serde_yaml::Value::deserialize(doc).map_err(|e| {
+                    let loc = e.location();
+                    ParseError {
+                        message: strip_location(e.to_string()),
+                        line: loc.as_ref().map_or(1, |l| l.line()),
+                        column: loc.as_ref().map_or(1, |l| l.column()),
+                    }
+                })?;
+                docs.push(value);
+            }
+            if docs.len() == 1 {
+                top_level(from_yaml("(root)".to_string(), docs.remove(0)))
+            } else {
+                docs.into_iter()
+                    .enumerate()
+                    .map(|(i, v)| from_yaml(format!("document {}", i + 1), v))
+                    .collect()
+            }
+        }
+        DataFormat::Toml => {
+            let table: toml::Table = text.parse().map_err(|e: toml::de::Error| {
+                let (line, column) = e.span().map_or((1, 1), |span| line_col(text, span.start));
+                ParseError {
+                    message: e.message().to_string(),
+                    line,
+                    column,
+                }
+            })?;
+            table.into_iter().map(|(k, v)| from_toml(k, v)).collect()
+        }
+        DataFormat::Csv | DataFormat::Tsv => Vec::new(),
+    };
+    for node in nodes.iter_mut() {
+        node.is_expanded = true;
+    }
+    Ok(nodes)
+}
+
+// The children of a container root are shown at the top level; a scalar document
+// stays a single node.
+fn top_level(root: DataNode) -> Vec<DataNode> {
+    if root.children.is_empty() {
+        vec![root]
+    } else {
+        root.children
+    }
+}
+
+fn from_json(key: String, value: serde_json::Value) -> DataNode {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => {
+            let children = map.into_iter().map(|(k, v)| from_json(k, v)).collect();
+            DataNode::branch(key, '{', '}', children)
+        }
+        Value::Array(items) => {
+            let children = items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| from_json(format!("[{}]", i), v))
+                .collect();
+            DataNode::branch(key, '[', ']', children)
+        }
+        other => DataNode::leaf(key, other.to_string()),
+    }
+}
+
+fn from_yaml(key: String, value: serde_yaml::Value) -> DataNode {
+    use serde_yaml::Value;
+    match value {
+        Value::Mapping(map) => {
+            let children = map
+                .into_iter()
+                .map(|(k, v)| from_yaml(yaml_key(&k), v))
+                .collect();
+            DataNode::branch(key, '{', '}', children)
+        }
+        Value::Sequence(items) => {
+            let children = items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| from_yaml(format!("[{}]", i), v))
+                .collect();
+            DataNode::branch(key, '[', ']', children)
+        }
+        Value::Tagged(tagged) => {
+            let mut node = from_yaml(key, tagged.value);
+            node.value = format!("{} {}", tagged.tag, node.value);
+            node
+        }
+        other => DataNode::leaf(key, yaml_scalar(&other)),
+    }
+}
+
+// Keys are shown bare, like JSON and TOML keys; other scalars as written.
+fn yaml_key(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        other => yaml_scalar(other),
+    }
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> String {
+    use serde_yaml::Value;
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format!("{:?}", s),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn from_toml(key: String, value: toml::Value) -> DataNode {
+    use toml::Value;
+    match value {
+        Value::Table(table) => {
+            let children = table.into_iter().map(|(k, v)| from_toml(k, v)).collect();
+            DataNode::branch(key, '{', '}', children)
+        }
+        Value::Array(items) => {
+            let children = items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| from_toml(format!("[{}]", i), v))
+                .collect();
+            DataNode::branch(key, '[', ']', children)
+        }
+        other => DataNode::leaf(key, other.to_string()),
+    }
+}
+
+// serde_json and serde_yaml append "at line L column C" to their messages; the
+// location is shown separately.
+fn strip_location(mut message: String) -> String {
+    if let Some(i) = message.find(" at line ") {
+        message.truncate(i);
+    }
+    message
+}
+
+// 1-based line and column of a byte offset.
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |nl| &before[nl + 1..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub widths: Vec<usize>, // display width of each column
+}
+
+// Parse delimited text into a table. When `complete` is false the text is a prefix
+// of the file, so a trailing partial record is dropped.
+pub fn parse_table(
+    format: DataFormat,
+    text: &str,
+    complete: bool,
+    max_rows: usize,
+) -> Result<Table, ParseError> {
+    let delimiter = if format == DataFormat::Tsv {
+        b'\t'
+    } else {
+        b','
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut records = Vec::new();
+    for record in reader.records().take(max_rows + 1) {
+        let record = record.map_err(|e| {
+            let (line, column) = match e.position() {
+                Some(pos) => line_col(text, pos.byte() as usize),
+                None => (1, 1),
+            };
+            ParseError {
+                message: e.to_string(),
+                line,
+                column,
+            }
+        })?;
+        records.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    if !complete && records.len() <= max_rows {
+        records.pop();
+    }
+
+    let mut rows = records.into_iter();
+    let header = rows.next().unwrap_or_default();
+    let rows: Vec<Vec<String>> = rows.take(max_rows).collect();
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain([header.len()])
+        .max()
+        .unwrap_or(0);
+    let mut widths = vec![0; columns];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.width());
+        }
+    }
+    Ok(Table {
+        header,
+        rows,
+        widths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(format: DataFormat, text: &str) -> Vec<String> {
+        let nodes = parse_tree(format, text).expect("parses");
+        nodes.into_iter().map(|n| n.key).collect()
+    }
+
+    #[test]
+    fn keys_keep_file_order() {
+        let expected = ["zeta", "alpha", "mid"];
+        assert_eq!(
+            keys(DataFormat::Json, r#"{"zeta": 1, "alpha": 2, "mid": 3}"#),
+            expected
+        );
+        assert_eq!(
+            keys(DataFormat::Yaml, "zeta: 1\nalpha: 2\nmid: 3\n"),
+            expected
+        );
+        assert_eq!(
+            keys(DataFormat::Toml, "zeta = 1\nalpha = 2\nmid = 3\n"),
+            expected
+        );
+    }
+
+    #[test]
+    fn nested_keys_keep_file_order() {
+        let nodes = parse_tree(DataFormat::Toml, "[b]\ny = 1\nx = 2\n[a]\n").expect("parses");
+        assert_eq!(nodes[0].key, "b");
+        let inner: Vec<&str> = nodes[0].children.iter().map(|n| n.key.as_str()).collect();
+        assert_eq!(inner, ["y", "x"]);
+    }
+
+    #[test]
+    fn yaml_keys_are_bare() {
+        let nodes = parse_tree(DataFormat::Yaml, "\"quoted key\": \"v\"\n7: x\n").expect("parses");
+        assert_eq!(nodes[0].key, "quoted key");
+        assert_eq!(nodes[1].key, "7");
+    }
+
+    #[test]
+    fn table_widths_count_terminal_cells() {
+        let table = parse_table(DataFormat::Csv, "name,city\n東京タワー,é\n", true, 10).unwrap();
+        assert_eq!(table.widths, [10, 4]);
+    }
+}
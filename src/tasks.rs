@@ -0,0 +1,43 @@
+// This file is the channel background work reports back on. Workers are handed a
+// sender and the main loop drains whatever arrived once per tick, so nothing slow
+// ever runs between a key press and the next frame.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::du;
+use crate::hexview;
+use crate::loader;
+use crate::preview::Preview;
+use crate::transfer;
+
+pub enum TaskResult {
+    DirSize(du::Update),
+    DirBatch(loader::Batch),    // entries of a folder being expanded
+    Preview(PathBuf, Preview),  // loaded preview for a path
+    Changed(PathBuf),           // entries of a watched directory changed
+    Listing(loader::Listing),   // fresh entries of a loaded folder that changed
+    Transfer(transfer::Update), // progress of a copy or move, or a question about it
+    HexFound(hexview::Found),   // outcome of a search in the hex viewer
+}
+
+pub struct Tasks {
+    tx: Sender<TaskResult>,
+    rx: Receiver<TaskResult>,
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+
+    pub fn sender(&self) -> Sender<TaskResult> {
+        self.tx.clone()
+    }
+
+    // Results that arrived since the last call, without waiting.
+    pub fn drain(&self) -> Vec<TaskResult> {
+        self.rx.try_iter().collect()
+    }
+}
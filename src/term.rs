@@ -0,0 +1,44 @@
+// This file sets up the terminal the UI is drawn on. Everything is written to the
+// controlling terminal rather than stdout, so stdout stays free for picker output.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{Terminal, backend::CrosstermBackend};
+
+pub type Tty = Terminal<CrosstermBackend<File>>;
+
+#[cfg(target_family = "unix")]
+const TTY_PATH: &str = "/dev/tty";
+#[cfg(target_family = "windows")]
+const TTY_PATH: &str = "CONOUT$";
+
+fn open_tty() -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(TTY_PATH)
+}
+
+pub fn init() -> io::Result<Tty> {
+    let mut tty = open_tty()?;
+    enable_raw_mode()?;
+    execute!(tty, EnterAlternateScreen, EnableMouseCapture)?;
+
+    // Leave the terminal usable if we panic while the UI is up
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+
+    Terminal::new(CrosstermBackend::new(tty))
+}
+
+pub fn restore() -> io::Result<()> {
+    let mut tty = open_tty()?;
+    execute!(tty, DisableMouseCapture, LeaveAlternateScreen)?;
+    disable_raw_mode()
+}
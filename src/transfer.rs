@@ -0,0 +1,800 @@
+// This file runs copies and moves on a worker thread. The worker adds up what it
+// has to do first so progress can be shown against a total, asks the UI what to do
+// whenever a destination already exists, and checks for cancellation between
+// chunks. Copies keep permissions and modification times; moves are renames, or a
+// copy and delete when they cross filesystems. Undoing and redoing journal entries
+// runs on the same worker, so moving things back shows progress and conflicts too.
+
+use std::fs::{self, Metadata};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::journal::Step;
+use crate::tasks::TaskResult;
+use crate::trash;
+
+const PROGRESS_EVERY: Duration = Duration::from_millis(100);
+const CHUNK: usize = 256 * 1024; // bytes copied between cancellation checks
+
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub files: u64,
+    pub total_files: u64,
+    pub counting: bool,   // still adding up the totals
+    pub current: PathBuf, // entry being worked on
+}
+
+// One side of a conflict.
+#[derive(Debug, Clone)]
+pub struct Side {
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Side {
+    fn of(meta: &Metadata) -> Self {
+        Self {
+            is_dir: meta.is_dir(),
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        }
+    }
+}
+
+// A destination that already exists, waiting for the user to choose.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub source: Side,
+    pub existing: Side,
+    pub same: Option<bool>, // whether the contents match, once compared
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Overwrite,
+    Skip,
+    Rename, // put it next to the existing one under a free name
+}
+
+enum Answer {
+    Choose(Choice, bool), // and whether it goes for every later conflict too
+    Compare,
+}
+
+pub enum Update {
+    Progress(Progress),
+    Conflict(Conflict),
+    Done(Done),
+}
+
+pub struct Done {
+    pub steps: Vec<Step>,      // what went through, for the undo journal
+    pub left: Vec<Step>,       // journal steps that weren't replayed
+    pub last: Option<PathBuf>, // last entry placed, to select
+    pub skipped: usize,
+    pub result: io::Result<()>,
+}
+
+// A running copy or move, as the UI sees it.
+pub struct Transfer {
+    pub moving: bool,
+    pub undo: Option<bool>, // whether undoing, when replaying a journal entry
+    pub label: String,      // status message for when it is done
+    pub dirs: Vec<PathBuf>, // directories to re-read afterwards
+    pub progress: Progress,
+    pub conflict: Option<Conflict>,
+    pub comparing: bool,    // waiting for the worker to compare the conflict
+    pub apply_to_all: bool, // the next choice goes for every later conflict
+    answers: Sender<Answer>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Transfer {
+    // Copy (or move) each source to its destination on a new worker.
+    pub fn start(
+        moving: bool,
+        pairs: Vec<(PathBuf, PathBuf)>,
+        label: String,
+        dirs: Vec<PathBuf>,
+        tx: Sender<TaskResult>,
+    ) -> Self {
+        Self::spawn(moving, None, label, dirs, tx, move |worker| {
+            worker.run(pairs)
+        })
+    }
+
+    // Undo (or redo) the steps of a journal entry, in the order given, on a new
+    // worker. The folders to re-read come with the outcome instead.
+    pub fn replay(label: String, steps: Vec<Step>, undo: bool, tx: Sender<TaskResult>) -> Self {
+        let moving = steps
+            .iter()
+            .any(|step| step.transfer(undo).is_some_and(|(moving, ..)| moving));
+        let job = move |worker: &mut Worker| worker.replay(steps, undo);
+        Self::spawn(moving, Some(undo), label, Vec::new(), tx, job)
+    }
+
+    fn spawn(
+        moving: bool,
+        undo: Option<bool>,
+        label: String,
+        dirs: Vec<PathBuf>,
+        tx: Sender<TaskResult>,
+        job: impl FnOnce(&mut Worker) + Send + 'static,
+    ) -> Self {
+        let (answers, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut worker = Worker::new(moving, tx, rx, cancel.clone());
+        thread::spawn(move || job(&mut worker));
+        Self {
+            moving,
+            undo,
+            label,
+            dirs,
+            progress: Progress {
+                counting: true,
+                ..Progress::default()
+            },
+            conflict: None,
+            comparing: false,
+            apply_to_all: false,
+            answers,
+            cancel,
+        }
+    }
+
+    // What the progress dialog says is going on.
+    pub fn doing(&self) -> &'static str {
+        match self.undo {
+            Some(true) => "Undoing",
+            Some(false) => "Redoing",
+            None if self.moving => "Moving",
+            None => "Copying",
+        }
+    }
+
+    // Take in a report from the worker. Returns the outcome once it has finished.
+    pub fn receive(&mut self, update: Update) -> Option<Done> {
+        match update {
+            Update::Progress(progress) => self.progress = progress,
+            Update::Conflict(conflict) => {
+                self.comparing = false;
+                self.conflict = Some(conflict);
+            }
+            Update::Done(done) => return Some(done),
+        }
+        None
+    }
+
+    // Settle the open conflict.
+    pub fn choose(&mut self, choice: Choice) {
+        if self.conflict.take().is_some() {
+            let _ = self.answers.send(Answer::Choose(choice, self.apply_to_all));
+        }
+    }
+
+    // Ask the worker to check whether both sides of the conflict hold the same data.
+    pub fn compare(&mut self) {
+        if self.conflict.as_ref().is_some_and(|c| c.same.is_none()) && !self.comparing {
+            self.comparing = true;
+            let _ = self.answers.send(Answer::Compare);
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.conflict = None;
+    }
+
+    pub fn canceled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Worker {
+    moving: bool,
+    tx: Sender<TaskResult>,
+    answers: Receiver<Answer>,
+    cancel: Arc<AtomicBool>,
+    progress: Progress,
+    last_sent: Instant,
+    always: Option<Choice>, // picked with "apply to all"
+    steps: Vec<Step>,
+    skipped: usize,
+    created: Vec<PathBuf>, // entries the current copy made, parents first
+    rename: fn(&Path, &Path) -> io::Result<()>, // fs::rename, unless a test stands in
+}
+
+impl Worker {
+    fn new(
+        moving: bool,
+        tx: Sender<TaskResult>,
+        answers: Receiver<Answer>,
+        cancel: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            moving,
+            tx,
+            answers,
+            cancel,
+            progress: Progress::default(),
+            last_sent: Instant::now(),
+            always: None,
+            steps: Vec::new(),
+            skipped: 0,
+            created: Vec::new(),
+            rename: |from, to| fs::rename(from, to),
+        }
+    }
+
+    // Add up the files and bytes below `sources` for the progress totals.
+    fn count<'a>(&mut self, sources: impl Iterator<Item = &'a Path>) {
+        self.progress.counting = true;
+        for src in sources {
+            let (files, bytes) = tally(src, &self.cancel);
+            self.progress.total_files += files;
+            self.progress.total_bytes += bytes;
+            self.report(false);
+        }
+        self.progress.counting = false;
+        self.report(true);
+    }
+
+    fn run(&mut self, pairs: Vec<(PathBuf, PathBuf)>) {
+        self.count(pairs.iter().map(|(src, _)| src.as_path()));
+
+        let mut last = None;
+        let mut result = Ok(());
+        for (src, dst) in &pairs {
+            match self.place(src, dst) {
+                Ok(placed) => last = placed.or(last),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        let done = Done {
+            steps: std::mem::take(&mut self.steps),
+            left: Vec::new(),
+            last,
+            skipped: self.skipped,
+            result,
+        };
+        let _ = self.tx.send(TaskResult::Transfer(Update::Done(done)));
+    }
+
+    // Undo (or redo) journal steps one after the other, stopping at the first
+    // failure. Copies and moves go through `place` like any transfer, asking about
+    // conflicts; skipped ones are left for later. The rest are done in place.
+    fn replay(&mut self, steps: Vec<Step>, undo: bool) {
+        self.count(
+            steps
+                .iter()
+                .filter_map(|s| s.transfer(undo))
+                .map(|(_, src, _)| src),
+        );
+        let (mut done, mut left) = (Vec::new(), Vec::new());
+        let mut result = Ok(());
+        let mut todo = steps.into_iter();
+        while let Some(step) = todo.next() {
+            let replayed = match step.transfer(undo) {
+                Some((moving, src, dst)) => {
+                    self.moving = moving;
+                    self.place_back(src, dst)
+                        .map(|placed| placed.map(|to| step.placed(undo, to)))
+                }
+                None => self.check().and_then(|_| step.replay(undo)).map(Some),
+            };
+            // an entry overwritten on the way stays in the trash, outside the journal
+            self.steps.clear();
+            match replayed {
+                Ok(Some(step)) => done.push(step),
+                Ok(None) => left.push(step),
+                Err(e) => {
+                    result = Err(e);
+                    left.push(step);
+                    left.extend(todo.by_ref());
+                }
+            }
+        }
+        let done = Done {
+            steps: done,
+            left,
+            last: None, // the journal knows what to show
+            skipped: self.skipped,
+            result,
+        };
+        let _ = self.tx.send(TaskResult::Transfer(Update::Done(done)));
+    }
+
+    // `place` for a replayed step: a merged folder the entry came from may have
+    // gone since.
+    fn place_back(&mut self, src: &Path, dst: &Path) -> io::Result<Option<PathBuf>> {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.place(src, dst)
+    }
+
+    // Put `src` at `dst`, asking what to do when something is already there.
+    // Folders landing on folders are merged. Returns where it ended up, or None
+    // when it was skipped.
+    fn place(&mut self, src: &Path, dst: &Path) -> io::Result<Option<PathBuf>> {
+        self.check()?;
+        let meta = fs::symlink_metadata(src)?;
+        if src == dst {
+            // copying into its own folder makes a second one next to it
+            if self.moving {
+                return Ok(Some(dst.to_path_buf()));
+            }
+            let to = free_name(dst);
+            self.transfer(src, &to)?;
+            return Ok(Some(to));
+        }
+        let Ok(existing) = fs::symlink_metadata(dst) else {
+            self.transfer(src, dst)?;
+            return Ok(Some(dst.to_path_buf()));
+        };
+        if meta.is_dir() && existing.is_dir() {
+            for entry in fs::read_dir(src)? {
+                let entry = entry?;
+                self.place(&entry.path(), &dst.join(entry.file_name()))?;
+            }
+            if self.moving {
+                let _ = fs::remove_dir(src); // stays when anything in it was skipped
+            }
+            return Ok(Some(dst.to_path_buf()));
+        }
+        match self.resolve(src, dst, &meta, &existing)? {
+            Choice::Skip => {
+                let (files, bytes) = tally(src, &self.cancel);
+                self.progress.files += files;
+                self.progress.bytes += bytes;
+                self.skipped += 1;
+                Ok(None)
+            }
+            Choice::Rename => {
+                let to = free_name(dst);
+                self.transfer(src, &to)?;
+                Ok(Some(to))
+            }
+            Choice::Overwrite => {
+                // the old entry goes to the trash so the overwrite can be undone
+                match trash::trash(dst) {
+                    Ok(file) => self.steps.push(Step::Trashed {
+                        original: dst.to_path_buf(),
+                        file,
+                    }),
+                    Err(_) => remove_tree(dst)?,
+                }
+                self.transfer(src, dst)?;
+                Ok(Some(dst.to_path_buf()))
+            }
+        }
+    }
+
+    // What to do about `dst` being there already: the choice made for all
+    // conflicts, or whatever the user picks in the dialog.
+    fn resolve(
+        &mut self,
+        src: &Path,
+        dst: &Path,
+        meta: &Metadata,
+        existing: &Metadata,
+    ) -> io::Result<Choice> {
+        if let Some(choice) = self.always {
+            return Ok(choice);
+        }
+        self.report(true);
+        let mut conflict = Conflict {
+            src: src.to_path_buf(),
+            dst: dst.to_path_buf(),
+            source: Side::of(meta),
+            existing: Side::of(existing),
+            same: None,
+        };
+        let _ = self
+            .tx
+            .send(TaskResult::Transfer(Update::Conflict(conflict.clone())));
+        loop {
+            match self.answers.recv_timeout(PROGRESS_EVERY) {
+                Ok(Answer::Choose(choice, all)) => {
+                    if all {
+                        self.always = Some(choice);
+                    }
+                    return Ok(choice);
+                }
+                Ok(Answer::Compare) => {
+                    conflict.same = Some(same_contents(src, dst, &self.cancel)?);
+                    let _ = self
+                        .tx
+                        .send(TaskResult::Transfer(Update::Conflict(conflict.clone())));
+                }
+                Err(RecvTimeoutError::Timeout) => self.check()?,
+                Err(RecvTimeoutError::Disconnected) => return Err(canceled()),
+            }
+        }
+    }
+
+    // Put `src` at `dst`, which is free. A partly copied entry is removed again on
+    // failure or cancellation, so every entry either arrives whole or not at all.
+    // Only what the copy made is removed: something that appeared at `dst` since
+    // the conflict check stays.
+    fn transfer(&mut self, src: &Path, dst: &Path) -> io::Result<()> {
+        let (from, to) = (src.to_path_buf(), dst.to_path_buf());
+        if self.moving {
+            let (files, bytes) = tally(src, &self.cancel);
+            match (self.rename)(src, dst) {
+                Ok(()) => {
+                    self.progress.files += files;
+                    self.progress.bytes += bytes;
+                    self.report(false);
+                    self.steps.push(Step::Moved { from, to });
+                    return Ok(());
+                }
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.created.clear();
+        if let Err(e) = self.copy_tree(src, dst) {
+            self.remove_created();
+            return Err(e);
+        }
+        if !self.moving {
+            self.steps.push(Step::Copied { from, to });
+            return Ok(());
+        }
+        if let Err(e) = remove_tree(src) {
+            // the copy is complete; only the original couldn't go away
+            self.steps.push(Step::Copied { from, to });
+            return Err(e);
+        }
+        self.steps.push(Step::Moved { from, to });
+        Ok(())
+    }
+
+    // Copy `src` to `dst`, folders recursively. Symlinks are copied as links.
+    fn copy_tree(&mut self, src: &Path, dst: &Path) -> io::Result<()> {
+        self.check()?;
+        let meta = fs::symlink_metadata(src)?;
+        self.progress.current = src.to_path_buf();
+        if meta.file_type().is_symlink() {
+            copy_link(src, dst)?;
+            self.created.push(dst.to_path_buf());
+            self.progress.files += 1;
+            self.report(false);
+            return Ok(());
+        }
+        if meta.is_file() {
+            return self.copy_file(src, dst, &meta);
+        }
+        if !meta.is_dir() {
+            // opening a fifo would block for good
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a regular file", src.display()),
+            ));
+        }
+        fs::create_dir(dst)?;
+        self.created.push(dst.to_path_buf());
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            self.copy_tree(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        // after the contents, which would bump the time and might need write access
+        if let Ok(modified) = meta.modified() {
+            set_dir_modified(dst, modified)?;
+        }
+        fs::set_permissions(dst, meta.permissions())
+    }
+
+    fn copy_file(&mut self, src: &Path, dst: &Path, meta: &Metadata) -> io::Result<()> {
+        let mut from = fs::File::open(src)?;
+        let mut to = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dst)?;
+        self.created.push(dst.to_path_buf());
+        let mut buf = vec![0; CHUNK];
+        loop {
+            self.check()?;
+            let n = match from.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            to.write_all(&buf[..n])?;
+            self.progress.bytes += n as u64;
+            self.report(false);
+        }
+        let mut times = fs::FileTimes::new();
+        if let Ok(modified) = meta.modified() {
+            times = times.set_modified(modified);
+        }
+        if let Ok(accessed) = meta.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        to.set_times(times)?;
+        to.set_permissions(meta.permissions())?;
+        self.progress.files += 1;
+        self.report(false);
+        Ok(())
+    }
+
+    // Take back what a failed copy made, innermost first. Folders only go once
+    // empty, so anything else put in them meanwhile is kept.
+    fn remove_created(&mut self) {
+        for path in self.created.drain(..).rev() {
+            let is_dir = fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir());
+            let _ = if is_dir {
+                fs::remove_dir(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+        }
+    }
+
+    fn check(&self) -> io::Result<()> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(canceled());
+        }
+        Ok(())
+    }
+
+    // Send the progress so far, at most every PROGRESS_EVERY unless `now`.
+    fn report(&mut self, now: bool) {
+        if now || self.last_sent.elapsed() >= PROGRESS_EVERY {
+            let progress = Update::Progress(self.progress.clone());
+            let _ = self.tx.send(TaskResult::Transfer(progress));
+            self.last_sent = Instant::now();
+        }
+    }
+}
+
+fn canceled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "canceled")
+}
+
+// Files (anything but folders) and bytes below `path`, stopping early on cancel.
+fn tally(path: &Path, cancel: &AtomicBool) -> (u64, u64) {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return (0, 0);
+    };
+    if !meta.is_dir() {
+        return (1, if meta.is_file() { meta.len() } else { 0 });
+    }
+    let mut total = (0, 0);
+    for entry in fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+    {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let (files, bytes) = tally(&entry.path(), cancel);
+        total.0 += files;
+        total.1 += bytes;
+    }
+    total
+}
+
+// Whether `a` and `b` are files with the same bytes.
+fn same_contents(a: &Path, b: &Path, cancel: &AtomicBool) -> io::Result<bool> {
+    let (ma, mb) = (fs::metadata(a)?, fs::metadata(b)?);
+    if !ma.is_file() || !mb.is_file() || ma.len() != mb.len() {
+        return Ok(false);
+    }
+    let (mut fa, mut fb) = (fs::File::open(a)?, fs::File::open(b)?);
+    let (mut ba, mut bb) = (vec![0; CHUNK], vec![0; CHUNK]);
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(canceled());
+        }
+        let n = fa.read(&mut ba)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        fb.read_exact(&mut bb[..n])?;
+        if ba[..n] != bb[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+// "name (2).ext", "name (3).ext", ...: the first that doesn't exist yet.
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| fs::symlink_metadata(p).is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn remove_tree(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(unix)]
+fn copy_link(src: &Path, dst: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(src)?, dst)
+}
+
+#[cfg(not(unix))]
+fn copy_link(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::copy(src, dst).map(|_| ())
+}
+
+#[cfg(unix)]
+fn set_dir_modified(dir: &Path, modified: SystemTime) -> io::Result<()> {
+    fs::File::open(dir)?.set_modified(modified)
+}
+
+// Directories can't be opened as files here; they keep the time of the copy.
+#[cfg(not(unix))]
+fn set_dir_modified(_dir: &Path, _modified: SystemTime) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scratch folder for one test, removed afterwards.
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "file-picker-transfer-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, rel: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // A worker nobody listens to.
+    fn worker(moving: bool) -> Worker {
+        Worker::new(moving, mpsc::channel().0, mpsc::channel().1, Arc::default())
+    }
+
+    fn a_while_ago() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_keep_permissions_and_times() {
+        use std::os::unix::fs::PermissionsExt;
+        let scratch = Scratch::new("keep");
+        let file = scratch.file("src/file", "data");
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(a_while_ago())
+            .unwrap();
+        let src = scratch.0.join("src");
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o750)).unwrap();
+        set_dir_modified(&src, a_while_ago()).unwrap();
+
+        let dst = scratch.0.join("dst");
+        worker(false).transfer(&src, &dst).unwrap();
+        for (path, mode) in [(dst.clone(), 0o750), (dst.join("file"), 0o640)] {
+            let meta = fs::metadata(&path).unwrap();
+            assert_eq!(
+                meta.permissions().mode() & 0o777,
+                mode,
+                "{}",
+                path.display()
+            );
+            assert_eq!(
+                meta.modified().unwrap(),
+                a_while_ago(),
+                "{}",
+                path.display()
+            );
+        }
+        assert_eq!(fs::read_to_string(dst.join("file")).unwrap(), "data");
+        assert!(file.exists());
+    }
+
+    #[test]
+    fn moves_across_filesystems_copy_then_delete() {
+        let scratch = Scratch::new("exdev");
+        scratch.file("src/inner/file", "data");
+        let (src, dst) = (scratch.0.join("src"), scratch.0.join("dst"));
+        let mut worker = worker(true);
+        worker.rename = |_, _| Err(io::Error::from(io::ErrorKind::CrossesDevices));
+
+        worker.transfer(&src, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("inner/file")).unwrap(), "data");
+        assert!(!src.exists());
+        assert!(
+            matches!(&worker.steps[..], [Step::Moved { from, to }] if *from == src && *to == dst)
+        );
+        assert_eq!(worker.progress.bytes, 4);
+    }
+
+    #[test]
+    fn keeps_what_appeared_at_the_destination() {
+        let scratch = Scratch::new("appeared");
+        let src = scratch.file("src/file", "ours");
+        let dst = scratch.file("theirs/file", "theirs");
+        let err = worker(false).transfer(&src, &dst).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "theirs");
+
+        let dir = scratch.0.join("theirs");
+        let err = worker(false)
+            .transfer(&scratch.0.join("src"), &dir)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "theirs");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_copies_take_back_only_their_own() {
+        let scratch = Scratch::new("failed");
+        scratch.file("src/a", "a");
+        let fifo = scratch.0.join("src/z-fifo");
+        let c_path = std::ffi::CString::new(fifo.to_str().unwrap()).unwrap();
+        // SAFETY: c_path is a valid NUL-terminated path
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+
+        let dst = scratch.0.join("dst");
+        let err = worker(false)
+            .transfer(&scratch.0.join("src"), &dst)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!dst.exists());
+        assert!(fifo.exists());
+    }
+}
@@ -0,0 +1,646 @@
+// This file moves entries to the trash as described by the freedesktop.org Trash
+// specification, and lists, restores and purges what is in there. Entries on the
+// home filesystem go to $XDG_DATA_HOME/Trash; entries on other mounts go to a trash
+// directory at the top of that mount, so nothing has to be copied across devices.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Something sitting in a trash directory.
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub trash: PathBuf,    // the trash directory holding it
+    pub name: OsString,    // its name under files/ (and info/, with .trashinfo)
+    pub original: PathBuf, // where it was deleted from
+    pub deleted: String,   // DeletionDate as recorded, local time
+    pub is_dir: bool,
+}
+
+impl Item {
+    // The item that `trash` put at `file` when deleting `original`, if it is still
+    // in the trash.
+    pub fn at(file: &Path, original: &Path) -> Option<Self> {
+        let meta = fs::symlink_metadata(file).ok()?;
+        Some(Self {
+            trash: file.parent()?.parent()?.to_path_buf(),
+            name: file.file_name()?.to_os_string(),
+            original: original.to_path_buf(),
+            deleted: String::new(),
+            is_dir: meta.is_dir(),
+        })
+    }
+
+    fn file(&self) -> PathBuf {
+        self.trash.join("files").join(&self.name)
+    }
+
+    fn info(&self) -> PathBuf {
+        self.trash.join("info").join(info_name(&self.name))
+    }
+}
+
+// State of the trash browser overlay.
+pub struct Browser {
+    pub items: Vec<Item>, // newest first
+    pub selected: usize,
+    pub confirm_purge: bool, // waiting for y/n before deleting the selected item for good
+    pub confirm_empty: bool, // waiting for y/n before deleting every item for good
+}
+
+impl Browser {
+    pub fn open() -> Self {
+        Self {
+            items: list(),
+            selected: 0,
+            confirm_purge: false,
+            confirm_empty: false,
+        }
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.items.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn current(&self) -> Option<&Item> {
+        self.items.get(self.selected)
+    }
+
+    // Drop the selected item from the list after it was restored or purged.
+    pub fn remove_current(&mut self) {
+        if self.selected < self.items.len() {
+            self.items.remove(self.selected);
+        }
+        self.move_selection(0);
+    }
+}
+
+// Move `path` into the trash for its filesystem. Returns where it ended up.
+pub fn trash(path: &Path) -> io::Result<PathBuf> {
+    let meta = fs::symlink_metadata(path)?;
+    let home = home_trash()?;
+    let (trash, top) = if device(&fs::metadata(&home)?) == device(&meta) {
+        (home, None)
+    } else {
+        let top = top_dir(path, device(&meta));
+        (top_trash(&top)?, Some(top))
+    };
+    let files = trash.join("files");
+    let info = trash.join("info");
+    make_dir(&files)?;
+    make_dir(&info)?;
+
+    // trash directories on other mounts record paths relative to their top
+    let recorded = match &top {
+        Some(top) => path.strip_prefix(top).unwrap_or(path),
+        None => path,
+    };
+    let (name, mut record) = reserve(&trash, path)?;
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode(recorded),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+    let target = files.join(&name);
+    let moved = record
+        .write_all(contents.as_bytes())
+        .and_then(|_| fs::rename(path, &target));
+    if let Err(e) = moved {
+        let _ = fs::remove_file(info.join(info_name(&name)));
+        return Err(e);
+    }
+    Ok(target)
+}
+
+// Everything in the home trash and the trash directories of mounted filesystems,
+// newest first.
+pub fn list() -> Vec<Item> {
+    let mut dirs: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+    if let Ok(home) = home_trash() {
+        dirs.push((home, None));
+    }
+    let uid = current_uid();
+    for top in mount_points() {
+        for trash in [
+            top.join(".Trash").join(uid.to_string()),
+            top.join(format!(".Trash-{}", uid)),
+        ] {
+            if trash.is_dir() && !dirs.iter().any(|(d, _)| *d == trash) {
+                dirs.push((trash, Some(top.clone())));
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    for (trash, top) in dirs {
+        let Ok(entries) = fs::read_dir(trash.join("info")) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("trashinfo")) {
+                continue;
+            }
+            let Some(name) = path.file_stem() else {
+                continue;
+            };
+            let Some((original, deleted)) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| parse_info(&text))
+            else {
+                continue;
+            };
+            let original = match &top {
+                Some(top) if original.is_relative() => top.join(original),
+                _ => original,
+            };
+            let file = trash.join("files").join(name);
+            // an info record whose file is gone has nothing left to restore
+            let Ok(meta) = fs::symlink_metadata(&file) else {
+                continue;
+            };
+            items.push(Item {
+                trash: trash.clone(),
+                name: name.to_os_string(),
+                original,
+                deleted,
+                is_dir: meta.is_dir(),
+            });
+        }
+    }
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+    items
+}
+
+// Put `item` back where it was deleted from, recreating missing parent folders.
+// Fails rather than replace something that took its place.
+pub fn restore(item: &Item) -> io::Result<()> {
+    if fs::symlink_metadata(&item.original).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", item.original.display()),
+        ));
+    }
+    if let Some(parent) = item.original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(item.file(), &item.original)?;
+    fs::remove_file(item.info())
+}
+
+// Delete `item` for good.
+pub fn purge(item: &Item) -> io::Result<()> {
+    let file = item.file();
+    if fs::symlink_metadata(&file)?.is_dir() {
+        fs::remove_dir_all(&file)?;
+    } else {
+        fs::remove_file(&file)?;
+    }
+    fs::remove_file(item.info())
+}
+
+// Delete all of `items` for good, carrying on past the ones that fail. Returns how
+// many went, or the first error.
+pub fn empty(items: &[Item]) -> io::Result<usize> {
+    let mut first = None;
+    let mut purged = 0;
+    for item in items {
+        match purge(item) {
+            Ok(()) => purged += 1,
+            Err(e) => {
+                first.get_or_insert(e);
+            }
+        }
+    }
+    first.map_or(Ok(purged), Err)
+}
+
+// $XDG_DATA_HOME/Trash, falling back to ~/.local/share/Trash. Created if missing.
+fn home_trash() -> io::Result<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    let trash = data_home.join("Trash");
+    make_dir(&trash)?;
+    Ok(trash)
+}
+
+// The trash directory for the mount at `top`: $top/.Trash/$uid when an admin set
+// up a shared sticky .Trash there, else $top/.Trash-$uid.
+fn top_trash(top: &Path) -> io::Result<PathBuf> {
+    let uid = current_uid();
+    let shared = top.join(".Trash");
+    if let Ok(meta) = fs::symlink_metadata(&shared)
+        && meta.is_dir()
+        && sticky(&meta)
+    {
+        let own = shared.join(uid.to_string());
+        if make_dir(&own).is_ok() {
+            return Ok(own);
+        }
+    }
+    let own = top.join(format!(".Trash-{}", uid));
+    make_dir(&own)?;
+    Ok(own)
+}
+
+// The mount point holding `path`: its highest ancestor on the same device.
+fn top_dir(path: &Path, dev: u64) -> PathBuf {
+    let mut top = path.parent().unwrap_or(path).to_path_buf();
+    for dir in path.ancestors().skip(2) {
+        match fs::metadata(dir) {
+            Ok(meta) if device(&meta) == dev => top = dir.to_path_buf(),
+            _ => break,
+        }
+    }
+    top
+}
+
+fn mount_points() -> Vec<PathBuf> {
+    let Ok(mounts) = fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+    mounts
+        .lines()
+        .filter_map(|line| line.split(' ').nth(1))
+        .map(unescape_mount)
+        .collect()
+}
+
+// Mount points escape spaces and the like as octal, e.g. "\040".
+fn unescape_mount(field: &str) -> PathBuf {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && let Some(code) = field
+                .get(i + 1..i + 4)
+                .and_then(|oct| u8::from_str_radix(oct, 8).ok())
+        {
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    path_from_bytes(out)
+}
+
+// Claim a name in `trash` for `path` by creating its .trashinfo file, adding a
+// counter when the plain name is taken in either info/ or files/.
+fn reserve(trash: &Path, path: &Path) -> io::Result<(OsString, fs::File)> {
+    let base = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "nothing to trash"))?;
+    for n in 1.. {
+        let mut name = base.to_os_string();
+        if n > 1 {
+            name.push(format!(".{}", n));
+        }
+        let info = trash.join("info").join(info_name(&name));
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info)
+        {
+            // something left in files/ without a record still owns the name
+            Ok(_) if fs::symlink_metadata(trash.join("files").join(&name)).is_ok() => {
+                let _ = fs::remove_file(&info);
+            }
+            Ok(file) => return Ok((name, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!()
+}
+
+// The name of the info record for the entry `name` under files/.
+fn info_name(name: &OsStr) -> OsString {
+    let mut info = name.to_os_string();
+    info.push(".trashinfo");
+    info
+}
+
+// The Path and DeletionDate keys of a .trashinfo file.
+fn parse_info(text: &str) -> Option<(PathBuf, String)> {
+    let mut lines = text.lines().map(str::trim);
+    if lines.next()? != "[Trash Info]" {
+        return None;
+    }
+    let (mut path, mut deleted) = (None, String::new());
+    for line in lines {
+        if let Some(value) = line.strip_prefix("Path=") {
+            path = Some(decode(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deleted = value.to_string();
+        }
+    }
+    Some((path?, deleted))
+}
+
+// Paths are stored URL-escaped, keeping '/' and unreserved characters as they are.
+fn encode(path: &Path) -> String {
+    let mut out = String::new();
+    for b in path_bytes(path) {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn decode(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(b) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(b);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    path_from_bytes(out)
+}
+
+// The trash is a unix affair; elsewhere these fall back to something harmless so
+// the rest of the program still builds.
+
+#[cfg(unix)]
+fn device(meta: &Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::dev(meta)
+}
+
+#[cfg(not(unix))]
+fn device(_meta: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn sticky(meta: &Metadata) -> bool {
+    std::os::unix::fs::MetadataExt::mode(meta) & 0o1000 != 0
+}
+
+#[cfg(not(unix))]
+fn sticky(_meta: &Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+    0
+}
+
+// Trash directories are private to their user.
+fn make_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+#[cfg(unix)]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec()
+}
+
+#[cfg(not(unix))]
+pub fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(<std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    // XDG_DATA_HOME is shared by the whole process, so tests take turns.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    // A scratch folder with its own home trash, removed afterwards.
+    struct Sandbox {
+        dir: PathBuf,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl Sandbox {
+        fn new(name: &str) -> Self {
+            let lock = ENV.lock().unwrap_or_else(|e| e.into_inner());
+            let dir =
+                env::temp_dir().join(format!("file-picker-trash-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("data")).unwrap();
+            // SAFETY: tests touching the environment hold ENV
+            unsafe { env::set_var("XDG_DATA_HOME", dir.join("data")) };
+            Self { dir, _lock: lock }
+        }
+
+        fn file(&self, rel: &str, contents: &str) -> PathBuf {
+            let path = self.dir.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+
+        fn trash(&self) -> PathBuf {
+            self.dir.join("data").join("Trash")
+        }
+
+        // What list() finds in this sandbox's trash, leaving out other trash
+        // directories on the machine.
+        fn items(&self) -> Vec<Item> {
+            list()
+                .into_iter()
+                .filter(|it| it.trash == self.trash())
+                .collect()
+        }
+    }
+
+    impl Drop for Sandbox {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn paths_are_percent_encoded() {
+        assert_eq!(
+            encode(Path::new("/tmp/a b/50%/é")),
+            "/tmp/a%20b/50%25/%C3%A9"
+        );
+        assert_eq!(
+            decode("/tmp/a%20b/50%25/%C3%A9"),
+            Path::new("/tmp/a b/50%/é")
+        );
+        let odd = path_from_bytes(b"/tmp/\xff\n".to_vec());
+        assert_eq!(encode(&odd), "/tmp/%FF%0A");
+        assert_eq!(decode(&encode(&odd)), odd);
+    }
+
+    #[test]
+    fn trash_writes_info_record() {
+        let sandbox = Sandbox::new("info");
+        let path = sandbox.file("some dir/my file", "x");
+        let target = trash(&path).unwrap();
+        assert_eq!(target, sandbox.trash().join("files").join("my file"));
+        assert!(target.exists() && !path.exists());
+
+        let info = fs::read_to_string(sandbox.trash().join("info/my file.trashinfo")).unwrap();
+        let lines: Vec<&str> = info.lines().collect();
+        assert_eq!(lines[0], "[Trash Info]");
+        assert_eq!(lines[1], format!("Path={}", encode(&path)));
+        assert!(lines[1].ends_with("/some%20dir/my%20file"));
+        let date = lines[2].strip_prefix("DeletionDate=").unwrap();
+        assert!(chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").is_ok());
+        assert_eq!(parse_info(&info).map(|(p, _)| p), Some(path));
+    }
+
+    #[test]
+    fn same_names_get_a_counter() {
+        let sandbox = Sandbox::new("names");
+        let first = sandbox.file("a/same", "1");
+        let second = sandbox.file("b/same", "2");
+        let third = sandbox.file("c/same", "3");
+        let files = sandbox.trash().join("files");
+        assert_eq!(trash(&first).unwrap(), files.join("same"));
+        assert_eq!(trash(&second).unwrap(), files.join("same.2"));
+        assert_eq!(trash(&third).unwrap(), files.join("same.3"));
+
+        let mut originals: Vec<(OsString, PathBuf)> = sandbox
+            .items()
+            .into_iter()
+            .map(|it| (it.name, it.original))
+            .collect();
+        originals.sort();
+        assert_eq!(
+            originals,
+            [
+                ("same".into(), first),
+                ("same.2".into(), second),
+                ("same.3".into(), third),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_taken_in_files_get_a_counter() {
+        let sandbox = Sandbox::new("orphan");
+        let files = sandbox.trash().join("files");
+        // left behind without an info record, e.g. by a crash mid-trash
+        fs::create_dir_all(&files).unwrap();
+        fs::write(files.join("same"), "orphan").unwrap();
+
+        let path = sandbox.file("same", "new");
+        assert_eq!(trash(&path).unwrap(), files.join("same.2"));
+        assert_eq!(fs::read_to_string(files.join("same")).unwrap(), "orphan");
+        assert!(!sandbox.trash().join("info/same.trashinfo").exists());
+        let items = sandbox.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "same.2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn odd_names_restore_from_their_own_file() {
+        let sandbox = Sandbox::new("odd");
+        let path = sandbox
+            .dir
+            .join(path_from_bytes(b"caf\xe9 \xff.txt".to_vec()));
+        fs::write(&path, "latin-1").unwrap();
+        // a lossy name would turn both bytes into the same replacement character
+        let twin = sandbox.file("caf\u{fffd} \u{fffd}.txt", "utf-8");
+        trash(&path).unwrap();
+        trash(&twin).unwrap();
+
+        let items = sandbox.items();
+        let item = items.iter().find(|it| it.original == path).unwrap();
+        assert_eq!(Path::new(&item.name), path.file_name().unwrap());
+        restore(item).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "latin-1");
+        assert!(!twin.exists());
+        assert_eq!(sandbox.items().len(), 1);
+    }
+
+    #[test]
+    fn restore_puts_it_back() {
+        let sandbox = Sandbox::new("restore");
+        let path = sandbox.file("gone/deep/file", "contents");
+        trash(&path).unwrap();
+        fs::remove_dir_all(sandbox.dir.join("gone")).unwrap();
+
+        let items = sandbox.items();
+        assert_eq!(items.len(), 1);
+        restore(&items[0]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "contents");
+        assert!(sandbox.items().is_empty());
+        assert!(!sandbox.trash().join("info/file.trashinfo").exists());
+    }
+
+    #[test]
+    fn restore_keeps_what_took_its_place() {
+        let sandbox = Sandbox::new("taken");
+        let path = sandbox.file("file", "old");
+        trash(&path).unwrap();
+        fs::write(&path, "new").unwrap();
+
+        let items = sandbox.items();
+        let err = restore(&items[0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(sandbox.items().len(), 1);
+        assert_eq!(fs::read_to_string(items[0].file()).unwrap(), "old");
+    }
+
+    #[test]
+    fn empty_deletes_everything() {
+        let sandbox = Sandbox::new("empty");
+        trash(&sandbox.file("file", "x")).unwrap();
+        sandbox.file("dir/inner/file", "y");
+        trash(&sandbox.dir.join("dir")).unwrap();
+
+        let items = sandbox.items();
+        assert_eq!(empty(&items).unwrap(), 2);
+        assert!(sandbox.items().is_empty());
+        assert_eq!(
+            fs::read_dir(sandbox.trash().join("files")).unwrap().count(),
+            0
+        );
+        assert_eq!(
+            fs::read_dir(sandbox.trash().join("info")).unwrap().count(),
+            0
+        );
+    }
+}
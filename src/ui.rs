@@ -1,46 +1,183 @@
+use std::time::SystemTime;
+
 use ratatui::{
     Frame,
-    layout::Rect,
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    App,
+    columns::{self, Column},
+    finder::Finder,
+    fs,
+    hexview::{self, HexView},
+    ops::{Action, Prompt},
+    picker,
+    preview::{self, Preview, PreviewCache},
+    transfer::{Conflict, Side, Transfer},
+    trash::Browser,
 };
 
-use crate::{App, fs};
+// Where the last frame put the parts that take mouse clicks.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Layout {
+    pub tree: Rect,         // rows of the tree list, inside its borders
+    pub menu: Option<Rect>, // rows of the open-with popup, inside its borders
+    pub menu_offset: usize, // first open-with item shown
+}
+
+impl Layout {
+    // The list row under a click at (`column`, `row`) in `area`, for a list
+    // scrolled down by `offset` rows.
+    pub fn row_at(area: Rect, offset: usize, column: u16, row: u16) -> Option<usize> {
+        area.contains(Position::new(column, row))
+            .then(|| offset + (row - area.y) as usize)
+    }
+}
 
-pub fn draw(frame: &mut Frame, app: &App) {
+// Draws the frame, scrolling the tree to keep the selection in view and recording
+// the layout in `app` for mouse hit-testing.
+pub fn draw(frame: &mut Frame, app: &mut App) {
     let area = frame.area();
 
-    // Main body (reserve bottom row for status)
+    if let Some(hex) = &app.hex {
+        draw_hex(frame, hex, &app.status, area);
+        return;
+    }
+
+    // Main body (reserve bottom row for status, plus one for the save input)
+    let reserved = if app.save.is_some() { 2 } else { 1 };
     let body_area = Rect {
         x: area.x,
         y: area.y,
         width: area.width,
-        height: area.height.saturating_sub(1),
+        height: area.height.saturating_sub(reserved),
+    };
+
+    // Tree on the left, preview of the selected entry on the right
+    let (tree_area, preview_area) = if app.preview_shown(area.width) {
+        let tree_w = body_area.width / 2;
+        let preview_area = Rect {
+            x: body_area.x + tree_w,
+            width: body_area.width - tree_w,
+            ..body_area
+        };
+        (
+            Rect {
+                width: tree_w,
+                ..body_area
+            },
+            Some(preview_area),
+        )
+    } else {
+        (body_area, None)
     };
 
-    // Build simple list of entries with icons from fs::icons (emoji-based)
-    let flat = fs::tree::flatten(&app.entries);
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+    app.layout.tree = block.inner(tree_area);
+
+    // Build simple list of entries with icons from fs::icons (emoji-based). Only the
+    // rows that fit are built.
+    let rows = app.layout.tree.height as usize;
+    app.offset = scrolled(app.offset, app.selected, rows, app.view.len());
+    let first = app.offset;
+    let flat = app.view.items(&app.entries, first, rows);
+    // metadata columns are right-aligned and dropped when names would get too cramped
+    let inner_w = tree_area.width.saturating_sub(2) as usize;
+    let columns = app.columns.fit(&flat, inner_w as u16, MIN_NAME);
+    let columns_w: usize = columns.iter().map(|(_, w)| w + 1).sum();
+    let now = SystemTime::now();
     let items: Vec<ListItem> = flat
         .iter()
         .map(|e| {
-            let icon = fs::icons::get_icon(e.is_dir, e.is_expanded);
             let indent = "  ".repeat(e.indent as usize); // two spaces per indent level
-            ListItem::new(format!("{indent}{icon} {}", e.name))
+            if e.placeholder {
+                return ListItem::new(Line::styled(
+                    format!(" {indent}{}", e.name),
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+            }
+            let icon = fs::icons::get_icon(e.is_dir, e.is_expanded);
+            let mark = if app.marked.contains(&e.path) {
+                "*"
+            } else {
+                " "
+            };
+            let mut spans = vec![Span::raw(format!("{mark}{indent}{icon} "))];
+            // while renaming, the new name is typed right where the old one was
+            if let Some(prompt) = app.prompt.as_ref().filter(|p| {
+                p.action == Action::Rename && !p.confirming && p.targets.first() == Some(&e.path)
+            }) {
+                spans.push(Span::styled(
+                    format!("{}_", prompt.input),
+                    Style::default().fg(Color::Yellow),
+                ));
+                return ListItem::new(Line::from(spans));
+            }
+            spans.extend(highlight_matches(&e.name, &e.matches));
+            // folder sizes show next to the name unless the size column is shown
+            if let Some(size) = e.usage
+                && !columns.iter().any(|(c, _)| *c == Column::Size)
+            {
+                spans.push(Span::styled(
+                    format!("  {}", columns::usage(size)),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            if columns.is_empty() {
+                return ListItem::new(Line::from(spans));
+            }
+
+            let mut spans = truncate_spans(spans, inner_w.saturating_sub(columns_w));
+            let used: usize = spans.iter().map(Span::width).sum();
+            let mut cells = " ".repeat(inner_w.saturating_sub(columns_w + used));
+            for (column, w) in &columns {
+                // cut and padded in terminal cells, wide characters count double
+                let mut left = *w;
+                let cell: String = app
+                    .columns
+                    .cell(*column, e, now)
+                    .chars()
+                    .take_while(|c| {
+                        let cw = c.width().unwrap_or(0);
+                        let fits = cw <= left;
+                        left = left.saturating_sub(cw);
+                        fits
+                    })
+                    .collect();
+                let pad = " ".repeat(w.saturating_sub(cell.width()));
+                // numbers line up on the right, text on the left
+                if *column == Column::Size {
+                    cells.push_str(&format!(" {}{}", pad, cell));
+                } else {
+                    cells.push_str(&format!(" {}{}", cell, pad));
+                }
+            }
+            spans.push(Span::styled(cells, Style::default().fg(Color::DarkGray)));
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
     let list = List::new(items)
-        .block(
-            Block::new()
-                .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White).bg(Color::Black)),
-        )
+        .block(block)
         .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
 
     let mut state = ListState::default();
-    state.select(app.selected);
+    state.select(app.selected.map(|i| i - first));
+
+    frame.render_stateful_widget(list, tree_area, &mut state);
 
-    frame.render_stateful_widget(list, body_area, &mut state);
+    if let (Some(preview_area), Some(cache)) = (preview_area, &app.preview) {
+        draw_preview(frame, cache, preview_area, app.focus_preview);
+    }
 
     // Status bar
     let status_area = Rect {
@@ -49,9 +186,102 @@ pub fn draw(frame: &mut Frame, app: &App) {
         width: area.width,
         height: 1,
     };
-    let status = Paragraph::new(app.status.as_str());
+    let status = match &app.fuzzy {
+        Some(f) if f.editing => {
+            Paragraph::new(format!("/{}_  ({} shown)", f.query, app.view.len()))
+                .style(Style::default().fg(Color::Yellow))
+        }
+        _ => Paragraph::new(app.status.as_str()),
+    };
     frame.render_widget(status, status_area);
 
+    // Current sort and listing modes, right-aligned in the status bar
+    let mut mode_label = app.list.sort.label();
+    if app.list.show_hidden {
+        mode_label.push_str(" | hidden shown");
+    }
+    if !app.list.respect_ignore {
+        mode_label.push_str(" | ignored shown");
+    }
+    // the "Filtering by" message expires, so keep the active filter visible here
+    if let Some(f) = app
+        .fuzzy
+        .as_ref()
+        .filter(|f| !f.editing && !f.query.is_empty())
+    {
+        mode_label.push_str(&format!(" | filter: {}", f.query));
+    }
+    if app.du.running() {
+        mode_label.push_str(&format!(" | sizing {}", app.du.pending()));
+    }
+    let label_w = (mode_label.chars().count() as u16).min(status_area.width);
+    let mode_area = Rect {
+        x: status_area.x + status_area.width - label_w,
+        width: label_w,
+        ..status_area
+    };
+    frame.render_widget(
+        Paragraph::new(mode_label).style(Style::default().fg(Color::DarkGray)),
+        mode_area,
+    );
+
+    if let Some(finder) = &app.finder {
+        draw_finder(frame, finder, area);
+    }
+
+    if let Some(browser) = &app.trash {
+        draw_trash(frame, browser, area);
+    }
+
+    if let Some(save) = &app.save {
+        let current = app.selected_item().filter(|it| !it.placeholder);
+        let selected = current.as_ref().map(|it| (it.path.as_path(), it.is_dir));
+        let dir = picker::SaveDialog::target_dir(&app.root, selected);
+        let input_area = Rect {
+            x: 0,
+            y: area.height.saturating_sub(2),
+            width: area.width,
+            height: 1,
+        };
+        let input = Paragraph::new(format!("Save as: {}/{}_", dir.display(), save.name))
+            .style(Style::default().fg(Color::Yellow));
+        frame.render_widget(input, input_area);
+
+        if let Some(target) = &save.confirm_overwrite {
+            let popup_w = (area.width.saturating_sub(10)).min(60);
+            let popup_h = 4.min(area.height);
+            let popup_area = Rect {
+                x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+                y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+                width: popup_w,
+                height: popup_h,
+            };
+            let prompt = Paragraph::new(format!(
+                "{}\nalready exists. Overwrite? (y/n)",
+                target.display()
+            ))
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .title("Confirm overwrite"),
+            );
+            frame.render_widget(Clear, popup_area);
+            frame.render_widget(prompt, popup_area);
+        }
+    }
+
+    if let Some(prompt) = &app.prompt {
+        draw_prompt(frame, prompt, area);
+    }
+
+    if let Some(transfer) = &app.transfer {
+        match &transfer.conflict {
+            Some(conflict) => draw_conflict(frame, transfer, conflict, area),
+            None => draw_transfer(frame, transfer, area),
+        }
+    }
+
+    app.layout.menu = None;
     if let Some(menu) = &app.open_menu {
         let popup_w = (area.width.saturating_sub(10)).min(60);
         let popup_h = (menu.items.len() as u16 + 2).min(area.height.saturating_sub(4));
@@ -69,12 +299,674 @@ pub fn draw(frame: &mut Frame, app: &App) {
             .iter()
             .map(|s| ListItem::new(s.clone()))
             .collect();
-        let mut state = ListState::default();
+        let mut state = ListState::default().with_offset(app.layout.menu_offset);
         state.select(Some(menu.selected));
         // small styling so popup stands out
+        let block = Block::new().borders(Borders::ALL).title("Open with");
+        app.layout.menu = Some(block.inner(popup_area));
         let list = List::new(items)
-            .block(Block::new().borders(Borders::ALL).title("Open with"))
+            .block(block)
             .highlight_style(Style::default().bg(Color::White).fg(Color::Black));
         frame.render_stateful_widget(list, popup_area, &mut state);
+        app.layout.menu_offset = state.offset(); // where the list scrolled to
+    }
+}
+
+// The first row to show in a list `rows` tall: `offset` stays put until the
+// selection moves out of view, and never leaves blank rows below the last entry.
+fn scrolled(offset: usize, selected: Option<usize>, rows: usize, len: usize) -> usize {
+    let mut offset = offset.min(len.saturating_sub(rows));
+    if let Some(i) = selected {
+        if i < offset {
+            offset = i;
+        } else if rows > 0 && i >= offset + rows {
+            offset = i + 1 - rows;
+        }
+    }
+    offset
+}
+
+fn draw_preview(frame: &mut Frame, cache: &PreviewCache, area: Rect, focused: bool) {
+    let title = cache
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut block = Block::new().borders(Borders::ALL).title(title);
+    if focused {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    let dim = Style::default().fg(Color::DarkGray);
+
+    let lines: Vec<Line> = match &cache.preview {
+        Preview::Text { lines, truncated } => {
+            let mut out = lines.clone();
+            if *truncated {
+                out.push(Line::styled("…", dim));
+            }
+            out
+        }
+        Preview::Directory {
+            entries,
+            dirs,
+            files,
+        } => {
+            let mut out = vec![Line::styled(
+                format!("{} folders, {} files", dirs, files),
+                dim,
+            )];
+            out.extend(entries.iter().map(|(name, is_dir)| {
+                Line::raw(format!("{} {}", fs::icons::get_icon(*is_dir, false), name))
+            }));
+            out
+        }
+        Preview::Binary { bytes, size } => {
+            let mut out = vec![Line::styled(format!("binary file, {} bytes", size), dim)];
+            out.extend(preview::hex_lines(bytes, 0).into_iter().map(Line::raw));
+            out
+        }
+        Preview::Data {
+            format,
+            nodes,
+            selected,
+        } => {
+            let block = block.title_bottom(Line::styled(
+                format!(" {} · Tab to browse ", format.label()),
+                dim,
+            ));
+            let items: Vec<ListItem> = preview::structured::flatten(nodes)
+                .into_iter()
+                .map(|row| {
+                    let marker = match (row.has_children, row.is_expanded) {
+                        (true, true) => "▾ ",
+                        (true, false) => "▸ ",
+                        (false, _) => "  ",
+                    };
+                    let indent = "  ".repeat(row.indent as usize);
+                    let value_style = if row.has_children {
+                        dim
+                    } else {
+                        Style::default().fg(Color::Green)
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::raw(format!("{indent}{marker}")),
+                        Span::styled(row.key, Style::default().fg(Color::Cyan)),
+                        Span::raw(": "),
+                        Span::styled(row.value, value_style),
+                    ]))
+                })
+                .collect();
+            let mut state = ListState::default();
+            state.select(Some(*selected));
+            let highlight = if focused {
+                Style::default().bg(Color::White).fg(Color::Black)
+            } else {
+                Style::default().add_modifier(Modifier::REVERSED | Modifier::DIM)
+            };
+            let list = List::new(items).block(block).highlight_style(highlight);
+            frame.render_stateful_widget(list, area, &mut state);
+            return;
+        }
+        Preview::Table {
+            format,
+            table,
+            truncated,
+        } => {
+            let mut out = vec![Line::styled(
+                format!("{} · {} rows", format.label(), table.rows.len()),
+                dim,
+            )];
+            let header_style = Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD);
+            out.push(Line::styled(
+                table_row(&table.header, &table.widths),
+                header_style,
+            ));
+            let rule: Vec<String> = table
+                .widths
+                .iter()
+                .map(|w| "─".repeat((*w).min(MAX_CELL)))
+                .collect();
+            out.push(Line::styled(rule.join("─┼─"), dim));
+            out.extend(
+                table
+                    .rows
+                    .iter()
+                    .map(|row| Line::raw(table_row(row, &table.widths))),
+            );
+            if *truncated {
+                out.push(Line::styled("…", dim));
+            }
+            out
+        }
+        Preview::Invalid {
+            format,
+            error,
+            lines,
+        } => {
+            let red = Style::default().fg(Color::Red);
+            let mut out = vec![Line::styled(
+                format!(
+                    "{} parse error at line {}, column {}",
+                    format.label(),
+                    error.line,
+                    error.column
+                ),
+                red.add_modifier(Modifier::BOLD),
+            )];
+            // errors at end of input may point one line past the last one
+            let at = error.line.clamp(1, lines.len().max(1));
+            for (i, line) in lines.iter().enumerate() {
+                out.push(line.clone());
+                // point at the error right under the offending line
+                if i + 1 == at {
+                    let caret = format!(
+                        "{}^ {}",
+                        " ".repeat(error.column.saturating_sub(1)),
+                        error.message
+                    );
+                    out.push(Line::styled(caret, red));
+                }
+            }
+            out
+        }
+        Preview::Error(e) => vec![Line::styled(e.as_str(), Style::default().fg(Color::Red))],
+        Preview::Loading => vec![Line::styled("loading…", dim)],
+    };
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+// Popup for a pending file operation: the question, then either the name being
+// typed or the entries about to change.
+fn draw_prompt(frame: &mut Frame, prompt: &Prompt, area: Rect) {
+    if prompt.action == Action::Rename && !prompt.confirming {
+        return; // typed inline in the tree
+    }
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::raw(prompt.question())];
+    if prompt.confirming {
+        const SHOWN: usize = 5;
+        for path in prompt.targets.iter().take(SHOWN) {
+            lines.push(Line::styled(format!("  {}", path.display()), dim));
+        }
+        if prompt.targets.len() > SHOWN {
+            lines.push(Line::styled(
+                format!("  … and {} more", prompt.targets.len() - SHOWN),
+                dim,
+            ));
+        }
+    } else {
+        lines.push(Line::styled(
+            format!("{}_", prompt.input),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    let popup_w = (area.width.saturating_sub(10)).min(60);
+    let popup_h = (lines.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+        width: popup_w,
+        height: popup_h,
+    };
+    let popup = Paragraph::new(lines).block(
+        Block::new()
+            .borders(Borders::ALL)
+            .title(prompt.action.label()),
+    );
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup, popup_area);
+}
+
+const MAX_CELL: usize = 30; // widest a table column is drawn
+
+// One table row with cells padded to their column width (capped at MAX_CELL).
+fn table_row(cells: &[String], widths: &[usize]) -> String {
+    widths
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let w = (*w).min(MAX_CELL);
+            let cell = cells.get(i).map(String::as_str).unwrap_or("");
+            // widths are terminal cells, so wide characters count double
+            let shown: String = if cell.width() > w {
+                let mut left = w.saturating_sub(1);
+                let fits = cell.chars().take_while(|c| {
+                    let cw = c.width().unwrap_or(0);
+                    let fits = cw <= left;
+                    left = left.saturating_sub(cw);
+                    fits
+                });
+                fits.chain(['…']).collect()
+            } else {
+                cell.to_string()
+            };
+            let pad = w.saturating_sub(shown.width());
+            format!("{}{}", shown, " ".repeat(pad))
+        })
+        .collect::<Vec<_>>()
+        .join(" │ ")
+}
+
+const MIN_NAME: u16 = 24; // room kept for indent, icon and name before columns
+
+// Cut spans down to `width` terminal cells, ending in "…" when anything was cut.
+fn truncate_spans(spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    let total: usize = spans.iter().map(Span::width).sum();
+    if total <= width {
+        return spans;
+    }
+    let mut out = Vec::new();
+    let mut left = width.saturating_sub(1);
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if w > left {
+                left = 0;
+                break;
+            }
+            left -= w;
+            text.push(c);
+        }
+        out.push(Span::styled(text, span.style));
+        if left == 0 {
+            break;
+        }
+    }
+    out.push(Span::raw("…"));
+    out
+}
+
+// Rows of bytes the hex viewer shows on a terminal `height` rows tall: everything
+// but the borders and the status bar.
+pub fn hex_rows(height: u16) -> u64 {
+    height.saturating_sub(3) as u64
+}
+
+fn draw_hex(frame: &mut Frame, hex: &HexView, status: &str, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let body_area = Rect {
+        height: area.height.saturating_sub(1),
+        ..area
+    };
+    let name = hex
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let percent = (hex.cursor + 1) * 100 / hex.size.max(1);
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .title(format!("{} — {} bytes", name, hex.size))
+        .title_bottom(Line::styled(
+            format!(
+                " 0x{:x} ({}) · {}%{} ",
+                hex.cursor,
+                hex.cursor,
+                percent.min(100),
+                if hex.searching() {
+                    " · searching…"
+                } else {
+                    ""
+                }
+            ),
+            dim,
+        ));
+
+    // wide enough for the largest offset, at least 8 digits
+    let digits = format!("{:x}", hex.size).len().max(8);
+    let match_len = hex.pattern.as_ref().map_or(0, Vec::len) as u64;
+    let byte_style = |offset: u64, b: u8| {
+        if offset == hex.cursor {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else if hex
+            .found
+            .is_some_and(|at| offset >= at && offset < at + match_len)
+        {
+            Style::default().bg(Color::Yellow).fg(Color::Black)
+        } else if b == 0 {
+            dim
+        } else if b.is_ascii_graphic() || b == b' ' {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Cyan)
+        }
+    };
+
+    let mut lines = Vec::new();
+    for (r, chunk) in hex.data.chunks(hexview::ROW as usize).enumerate() {
+        let base = hex.top + r as u64 * hexview::ROW;
+        let mut spans = vec![Span::styled(format!("{:0digits$x}  ", base), dim)];
+        for j in 0..hexview::ROW as usize {
+            if j == 8 {
+                spans.push(Span::raw(" "));
+            }
+            match chunk.get(j) {
+                Some(&b) => {
+                    spans.push(Span::styled(
+                        format!("{:02x}", b),
+                        byte_style(base + j as u64, b),
+                    ));
+                    spans.push(Span::raw(" "));
+                }
+                None => spans.push(Span::raw("   ")),
+            }
+        }
+        spans.push(Span::styled(" │", dim));
+        for (j, &b) in chunk.iter().enumerate() {
+            let c = if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            };
+            spans.push(Span::styled(c.to_string(), byte_style(base + j as u64, b)));
+        }
+        spans.push(Span::styled("│", dim));
+        lines.push(Line::from(spans));
+    }
+    if hex.size == 0 {
+        lines.push(Line::styled("empty file", dim));
+    }
+    frame.render_widget(Paragraph::new(lines).block(block), body_area);
+
+    let status_area = Rect {
+        y: area.height.saturating_sub(1),
+        height: 1,
+        ..area
+    };
+    let status = match &hex.input {
+        Some(input) => {
+            let prompt = match input.prompt {
+                hexview::Prompt::Goto => "Goto offset (decimal, 0x hex, +/- relative)",
+                hexview::Prompt::Search => "Search (hex bytes, or text; \" to force text)",
+            };
+            Paragraph::new(format!("{}: {}_", prompt, input.text))
+                .style(Style::default().fg(Color::Yellow))
+        }
+        None => Paragraph::new(status),
+    };
+    frame.render_widget(status, status_area);
+}
+
+fn draw_finder(frame: &mut Frame, finder: &Finder, area: Rect) {
+    let popup_w = (area.width.saturating_sub(4)).min(100);
+    let popup_h = area.height.saturating_sub(4);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+        width: popup_w,
+        height: popup_h,
+    };
+    let scanning = if finder.done { "" } else { ", scanning…" };
+    let title = format!(
+        "Find file ({}/{}{})",
+        finder.hits.len(),
+        finder.paths.len(),
+        scanning
+    );
+    let block = Block::new().borders(Borders::ALL).title(title);
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+    if inner.height == 0 {
+        return;
+    }
+
+    let input_area = Rect { height: 1, ..inner };
+    let input =
+        Paragraph::new(format!("> {}_", finder.query)).style(Style::default().fg(Color::Yellow));
+    frame.render_widget(input, input_area);
+
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height.saturating_sub(1),
+        ..inner
+    };
+    // only build rows for the part of the result list that can be seen
+    let rows = list_area.height as usize;
+    let first = finder.selected.saturating_sub(rows.saturating_sub(1));
+    let items: Vec<ListItem> = finder
+        .hits
+        .iter()
+        .skip(first)
+        .take(rows)
+        .map(|hit| {
+            ListItem::new(Line::from(highlight_matches(
+                &finder.paths[hit.index].to_string_lossy(),
+                &hit.matches,
+            )))
+        })
+        .collect();
+    let mut state = ListState::default();
+    if !finder.hits.is_empty() {
+        state.select(Some(finder.selected - first));
+    }
+    let list = List::new(items).highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+    frame.render_stateful_widget(list, list_area, &mut state);
+}
+
+fn draw_trash(frame: &mut Frame, browser: &Browser, area: Rect) {
+    let popup_w = (area.width.saturating_sub(4)).min(100);
+    let popup_h = area.height.saturating_sub(4);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+        width: popup_w,
+        height: popup_h,
+    };
+    let dim = Style::default().fg(Color::DarkGray);
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .title(format!("Trash ({} items)", browser.items.len()))
+        .title_bottom(Line::styled(
+            " Enter restores · Delete removes for good · Esc closes ",
+            dim,
+        ));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+    if browser.items.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Line::styled("The trash is empty", dim)),
+            inner,
+        );
+        return;
+    }
+
+    // only build rows for the part of the list that can be seen
+    let rows = inner.height as usize;
+    let first = browser.selected.saturating_sub(rows.saturating_sub(1));
+    let width = inner.width as usize;
+    let items: Vec<ListItem> = browser
+        .items
+        .iter()
+        .skip(first)
+        .take(rows)
+        .map(|item| {
+            // deletion date on the right, the original path cut short to make room
+            let date = item.deleted.replace('T', " ");
+            let icon = fs::icons::get_icon(item.is_dir, false);
+            let path = vec![Span::raw(format!("{} {}", icon, item.original.display()))];
+            let mut spans = truncate_spans(path, width.saturating_sub(date.len() + 1));
+            let used: usize = spans.iter().map(Span::width).sum();
+            spans.push(Span::raw(
+                " ".repeat(width.saturating_sub(used + date.len())),
+            ));
+            spans.push(Span::styled(date, dim));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(browser.selected - first));
+    let list = List::new(items).highlight_style(Style::default().bg(Color::White).fg(Color::Black));
+    frame.render_stateful_widget(list, inner, &mut state);
+}
+
+// Progress of a copy or move: the entry being worked on, a bar for the bytes and
+// the counts behind it.
+fn draw_transfer(frame: &mut Frame, transfer: &Transfer, area: Rect) {
+    let popup_w = (area.width.saturating_sub(10)).min(60);
+    let popup_h = 5.min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+        width: popup_w,
+        height: popup_h,
+    };
+    let dim = Style::default().fg(Color::DarkGray);
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .title(transfer.doing())
+        .title_bottom(Line::styled(" Esc cancels ", dim));
+    let inner = block.inner(popup_area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(block, popup_area);
+
+    let p = &transfer.progress;
+    let row = |i: u16| Rect {
+        y: inner.y + i,
+        height: 1,
+        ..inner
+    };
+    if inner.height < 3 {
+        return;
+    }
+    let current = vec![Span::raw(p.current.display().to_string())];
+    frame.render_widget(
+        Paragraph::new(Line::from(truncate_spans(current, inner.width as usize))).style(dim),
+        row(0),
+    );
+    let ratio = if p.total_bytes > 0 {
+        p.bytes as f64 / p.total_bytes as f64
+    } else if p.total_files > 0 {
+        p.files as f64 / p.total_files as f64
+    } else {
+        0.0
+    };
+    frame.render_widget(
+        Gauge::default()
+            .ratio(ratio.min(1.0))
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::DarkGray)),
+        row(1),
+    );
+    let counts = if p.counting {
+        format!(
+            "Counting… {} files, {}",
+            p.total_files,
+            columns::human_size(p.total_bytes)
+        )
+    } else {
+        format!(
+            "{} of {} · {} of {} files",
+            columns::human_size(p.bytes),
+            columns::human_size(p.total_bytes),
+            p.files,
+            p.total_files
+        )
+    };
+    frame.render_widget(Paragraph::new(counts), row(2));
+}
+
+// The question asked when a destination already exists: both sides side by side,
+// the result of comparing them once asked for, and the choices.
+fn draw_conflict(frame: &mut Frame, transfer: &Transfer, conflict: &Conflict, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let name = conflict
+        .dst
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let folder = conflict
+        .dst
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let side = |label: &str, side: &Side| {
+        let size = if side.is_dir {
+            "folder".to_string()
+        } else {
+            columns::human_size(side.size)
+        };
+        let modified = side
+            .modified
+            .map(columns::absolute_time)
+            .unwrap_or_default();
+        Line::raw(format!("  {:<9} {:>6}  {}", label, size, modified))
+    };
+    let compared = match conflict.same {
+        _ if transfer.comparing => Line::styled("  Comparing…", dim),
+        Some(true) => Line::styled("  Same contents", Style::default().fg(Color::Green)),
+        Some(false) => Line::styled("  Contents differ", Style::default().fg(Color::Yellow)),
+        None => Line::raw(""),
+    };
+    let lines = vec![
+        Line::raw(format!("{} already exists in {}", name, folder)),
+        Line::raw(""),
+        side(transfer.doing(), &conflict.source),
+        side("Existing", &conflict.existing),
+        compared,
+        Line::raw(""),
+        Line::raw(format!(
+            "[{}] a: do the same for all remaining conflicts",
+            if transfer.apply_to_all { "x" } else { " " }
+        )),
+    ];
+
+    let popup_w = (area.width.saturating_sub(10)).min(64);
+    let popup_h = (lines.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width.saturating_sub(popup_w)) / 2,
+        y: area.y + (area.height.saturating_sub(popup_h)) / 2,
+        width: popup_w,
+        height: popup_h,
+    };
+    let popup = Paragraph::new(lines).block(
+        Block::new()
+            .borders(Borders::ALL)
+            .title("Already exists")
+            .title_bottom(Line::styled(
+                " o overwrite · s skip · r rename · c compare · Esc cancels ",
+                dim,
+            )),
+    );
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(popup, popup_area);
+}
+
+// Split `name` into spans, styling the chars at the fuzzy-matched indices.
+fn highlight_matches(name: &str, matches: &[usize]) -> Vec<Span<'static>> {
+    if matches.is_empty() {
+        return vec![Span::raw(name.to_string())];
+    }
+    let hit = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_hit = false;
+    for (i, c) in name.chars().enumerate() {
+        let is_hit = matches.contains(&i);
+        if is_hit != run_hit && !run.is_empty() {
+            let text = std::mem::take(&mut run);
+            spans.push(if run_hit {
+                Span::styled(text, hit)
+            } else {
+                Span::raw(text)
+            });
+        }
+        run_hit = is_hit;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if run_hit {
+            Span::styled(run, hit)
+        } else {
+            Span::raw(run)
+        });
     }
+    spans
 }
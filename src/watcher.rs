@@ -0,0 +1,107 @@
+// This file keeps the tree live: every expanded directory (and the root) is watched
+// with inotify, and changes are collected until things go quiet for a moment so a
+// burst of events, like a build or a `git checkout`, turns into one refresh.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::fs::tree::FileNode;
+use crate::tasks::TaskResult;
+
+const QUIET: Duration = Duration::from_millis(200); // refresh once no events came for this long
+const MAX_DELAY: Duration = Duration::from_secs(1); // but never hold changes back longer
+
+pub struct Watcher {
+    inner: Option<RecommendedWatcher>, // None when inotify isn't available
+    watched: HashSet<PathBuf>,
+    pending: HashSet<PathBuf>, // directories whose entries changed
+    first: Option<Instant>,    // when the oldest pending change came in
+    last: Instant,             // when the newest one did
+    resync: bool,              // folders were expanded or collapsed since the last sync
+}
+
+impl Watcher {
+    pub fn new(tx: Sender<TaskResult>) -> Self {
+        let inner = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            // the entries that changed live in the watched directory, their parent
+            for dir in event.paths.iter().filter_map(|p| p.parent()) {
+                let _ = tx.send(TaskResult::Changed(dir.to_path_buf()));
+            }
+        });
+        Self {
+            inner: inner.ok(),
+            watched: HashSet::new(),
+            pending: HashSet::new(),
+            first: None,
+            last: Instant::now(),
+            resync: true,
+        }
+    }
+
+    // Note that folders were expanded or collapsed, or the tree was read again.
+    pub fn resync(&mut self) {
+        self.resync = true;
+    }
+
+    // Watch `root` and every expanded directory below it, and stop watching the ones
+    // that were collapsed or went away. Does nothing unless `resync` was called.
+    pub fn sync(&mut self, root: &Path, nodes: &[FileNode]) {
+        if !std::mem::take(&mut self.resync) {
+            return;
+        }
+        let Some(inner) = self.inner.as_mut() else {
+            return;
+        };
+        let mut wanted = HashSet::from([root.to_path_buf()]);
+        expanded_dirs(nodes, &mut wanted);
+        if wanted == self.watched {
+            return;
+        }
+        for dir in self.watched.difference(&wanted) {
+            let _ = inner.unwatch(dir);
+        }
+        // directories that can't be watched just don't refresh live
+        for dir in wanted.difference(&self.watched) {
+            let _ = inner.watch(dir, RecursiveMode::NonRecursive);
+        }
+        self.watched = wanted;
+    }
+
+    // Note a change reported for `dir`.
+    pub fn changed(&mut self, dir: PathBuf) {
+        if self.watched.contains(&dir) {
+            self.pending.insert(dir);
+            self.first.get_or_insert_with(Instant::now);
+            self.last = Instant::now();
+        }
+    }
+
+    // The directories to re-read, once the changes have settled.
+    pub fn due(&mut self) -> Vec<PathBuf> {
+        let Some(first) = self.first else {
+            return Vec::new();
+        };
+        if self.last.elapsed() < QUIET && first.elapsed() < MAX_DELAY {
+            return Vec::new();
+        }
+        self.first = None;
+        self.pending.drain().collect()
+    }
+}
+
+fn expanded_dirs(nodes: &[FileNode], out: &mut HashSet<PathBuf>) {
+    for node in nodes.iter().filter(|n| n.is_dir && n.is_expanded) {
+        out.insert(node.path.clone());
+        expanded_dirs(&node.children, out);
+    }
+}